@@ -4,6 +4,8 @@ use cursive::theme;
 use cursive::traits::*;
 use cursive::{Cursive, CursiveExt, Printer};
 use std::fs::File;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 fn make_style(f: (u8, u8, u8), b: (u8, u8, u8)) -> theme::ColorStyle {
     theme::ColorStyle {
@@ -12,8 +14,23 @@ fn make_style(f: (u8, u8, u8), b: (u8, u8, u8)) -> theme::ColorStyle {
     }
 }
 
-fn position_to_char_idx(b: &Buffer, p: Position) -> usize {
-    b.get_rope().line_to_char(p.line() - 1) + p.col() - 1
+const TAB_WIDTH: usize = 4;
+
+/// Replace tabs of `text` displayed from column `x` with spaces up to tab stops
+fn expand_tabs(text: &str, x: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut x = x;
+    for grapheme in text.graphemes(true) {
+        if grapheme == "\t" {
+            let width = TAB_WIDTH - x % TAB_WIDTH;
+            result.extend(std::iter::repeat(' ').take(width));
+            x += width;
+        } else {
+            result.push_str(grapheme);
+            x += grapheme.width().max(1);
+        }
+    }
+    result
 }
 
 type ColoredInterval<'a> = (Position<'a>, Position<'a>, IntervalColor);
@@ -104,6 +121,7 @@ fn main() {
     let sample_file = File::open("test_data/edi.txt").unwrap();
 
     let mut buffer = Buffer::from_reader(sample_file).unwrap();
+    buffer.set_tab_width(TAB_WIDTH);
     buffer.move_right(11, false);
     buffer.move_right(2, true);
     buffer.swap_cursor();
@@ -148,13 +166,15 @@ impl View for KeyCodeView {
 
         for (from, to, color) in selections_colors.into_iter() {
             let ends_on_nl = to.is_line_end();
+            let slice_end = if ends_on_nl {
+                to.char_idx()
+            } else {
+                to.successor().unwrap().char_idx()
+            };
             let mut slice: String = self
                 .buffer
                 .get_rope()
-                .slice(
-                    position_to_char_idx(&self.buffer, from.clone())
-                        ..=position_to_char_idx(&self.buffer, to) - if ends_on_nl { 1 } else { 0 },
-                )
+                .slice(from.char_idx()..slice_end)
                 .to_string();
             if ends_on_nl {
                 slice.push(' ');
@@ -166,7 +186,8 @@ impl View for KeyCodeView {
                     IntervalColor::Cursor => make_style((5, 5, 5), (0, 0, 0)),
                 },
                 |printer| {
-                    printer.print((from.col() - 1, from.line() - 1), &slice);
+                    let x = self.buffer.display_col(from.clone(), TAB_WIDTH);
+                    printer.print((x, from.line() - 1), &expand_tabs(&slice, x));
                 },
             );
         }
@@ -192,8 +213,11 @@ impl View for KeyCodeView {
                     self.buffer.delete();
                 }
                 event::Key::Backspace => {
-                    self.buffer.delete();
-                    self.buffer.move_left(1, false);
+                    let _ = self.buffer.transaction(|buffer| -> Result<(), ()> {
+                        buffer.delete();
+                        buffer.move_left(1, false);
+                        Ok(())
+                    });
                 }
                 _ => {}
             },
@@ -218,7 +242,9 @@ impl View for KeyCodeView {
                 }
                 _ => {}
             },
-            Event::Char(c) => self.buffer.insert(&c.to_string()),
+            Event::Char(c) => {
+                self.buffer.insert(&c.to_string());
+            }
             _ => {}
         }
         EventResult::Consumed(None)
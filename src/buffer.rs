@@ -1,3 +1,12 @@
+use crate::changes::{Assoc, ChangeSet};
+use crate::history::{History, Step};
+use crate::layout::{Layout, VisualRow, WrapMode};
+use crate::text::{
+    auto_indent, col_at_display_col, display_col, find_char, find_matches, first_non_blank,
+    graphemes_count, line_break, line_content_end, matching_bracket, next_grapheme_boundary,
+    prev_grapheme_boundary, split_by_lines, split_by_matches, AutoIndent, IndentUnit, LineEnding,
+    WordChars, Words, BOM, BRACKET_PAIRS,
+};
 use crate::{
     selections::{
         storage::{SelectionIntersect, SelectionStorage},
@@ -6,15 +15,25 @@ use crate::{
     Delta,
 };
 use crate::{
-    selections::{Position, Selection},
-    DeltaType,
+    selections::{CursorDirection, Position, Selection},
+    text_object::{ObjectScope, TextObject},
+    DeltaType, Viewport,
 };
-use crate::{LineLength, Result};
-use itertools::Itertools;
+use crate::{Error, LineLength, Regex, Registers, Result};
+use line_changes::LineChanges;
 use ropey::Rope;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
+use std::fs::{self, File, OpenOptions};
 use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
+mod line_changes;
 #[cfg(test)]
 mod tests;
 
@@ -25,6 +44,33 @@ mod tests;
 pub struct Buffer {
     rope: Rope,
     selection_storage: SelectionStorage,
+    history: History,
+    line_ending: LineEnding,
+    /// Lines affected within open transactions, nested ones are on top
+    transactions: Vec<LineChanges>,
+    /// Changes of the text made by the last operation
+    last_change: ChangeSet,
+    /// Chars words are made of
+    word_chars: WordChars,
+    /// Opening and closing brackets to match
+    bracket_pairs: Vec<(char, char)>,
+    /// Whether char search goes beyond the cursor's line
+    find_across_lines: bool,
+    /// The last char search to repeat
+    last_find: Option<CharSearch>,
+    /// Unit lines are indented with
+    indent_unit: IndentUnit,
+    /// How lines started by `insert` are indented
+    auto_indent: AutoIndent,
+    /// Display columns between tab stops used to keep up/down movements
+    /// visually aligned
+    tab_width: usize,
+    /// Visual rows lines are wrapped into
+    layout: Layout,
+    /// Visible part of the text following the main cursor
+    viewport: Option<Viewport>,
+    /// Whether the text is written with a byte order mark
+    bom: bool,
 }
 
 #[cfg(not(test))]
@@ -52,13 +98,31 @@ impl Buffer {
     pub fn empty() -> Self {
         let rope = Rope::from_str("");
         Buffer {
-            rope,
             selection_storage: SelectionStorage::new(),
+            history: History::new(Default::default()),
+            line_ending: Default::default(),
+            transactions: vec![],
+            last_change: ChangeSet::new(),
+            word_chars: Default::default(),
+            bracket_pairs: BRACKET_PAIRS.to_vec(),
+            find_across_lines: false,
+            last_find: None,
+            indent_unit: Default::default(),
+            auto_indent: Default::default(),
+            tab_width: 4,
+            layout: Layout::new(&rope, None, 4),
+            viewport: None,
+            bom: false,
+            rope,
         }
     }
 
     /// Create `Buffer` from `Reader`.
     ///
+    /// The most used line ending of the text is picked as the buffer's one.
+    /// A byte order mark is not a part of the text, but it is remembered to
+    /// be written back.
+    ///
     /// ```
     /// # use coredit::Buffer;
     /// use std::fs::File;
@@ -68,13 +132,96 @@ impl Buffer {
     /// assert!(buffer.to_string().starts_with("That was easy"));
     /// ```
     pub fn from_reader<R: io::Read>(reader: R) -> Result<Self> {
-        let rope = Rope::from_reader(reader)?;
+        let mut rope = Rope::from_reader(reader)?;
+        let bom = rope.len_chars() > 0 && rope.char(0) == BOM;
+        if bom {
+            rope.remove(0..1);
+        }
+        let line_ending = LineEnding::detect(&rope);
+        let last_change = ChangeSet::identity(rope.len_chars());
         Ok(Buffer {
-            rope,
             selection_storage: SelectionStorage::new(),
+            history: History::new(line_ending),
+            line_ending,
+            transactions: vec![],
+            last_change,
+            word_chars: Default::default(),
+            bracket_pairs: BRACKET_PAIRS.to_vec(),
+            find_across_lines: false,
+            last_find: None,
+            indent_unit: Default::default(),
+            auto_indent: Default::default(),
+            tab_width: 4,
+            layout: Layout::new(&rope, None, 4),
+            viewport: None,
+            bom,
+            rope,
         })
     }
 
+    /// Write the text to `Writer` chunk by chunk, as it is stored, starting
+    /// with a byte order mark if the text was read with one.
+    ///
+    /// ```
+    /// # use coredit::Buffer;
+    /// let buffer = Buffer::from_reader("Hello\r\nworld".as_bytes()).unwrap();
+    /// let mut bytes = vec![];
+    /// buffer.write_to(&mut bytes).unwrap();
+    /// assert_eq!(bytes, b"Hello\r\nworld");
+    /// ```
+    pub fn write_to<W: io::Write>(&self, writer: W) -> Result<()> {
+        self.write_chunks(writer).map_err(Error::Write)
+    }
+
+    /// Save the text to the file at `path` atomically: it is written to
+    /// a temporary file next to it first, which then replaces the file.
+    /// Permissions of the replaced file are kept and symlinks are followed.
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let error = |source| Error::Save {
+            path: path.as_ref().to_owned(),
+            source,
+        };
+        let path = &fs::canonicalize(&path).unwrap_or_else(|_| path.as_ref().to_owned());
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| error(io::ErrorKind::InvalidInput.into()))?;
+        let (temp_path, file) = create_temp_file(path, file_name).map_err(error)?;
+
+        let result = self.write_temp_file(file).and_then(|_| {
+            if let Ok(metadata) = fs::metadata(path) {
+                fs::set_permissions(&temp_path, metadata.permissions())?;
+            }
+            fs::rename(&temp_path, path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result.map_err(error)
+    }
+
+    /// Write the byte order mark if needed and the text's chunks
+    fn write_chunks<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.bom {
+            write!(writer, "{}", BOM)?;
+        }
+        for chunk in self.rope.chunks() {
+            writer.write_all(chunk.as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Write the text to a new temporary file and make sure it reached the disk
+    fn write_temp_file(&self, file: File) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(file);
+        self.write_chunks(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+
+    /// Check if the text is written with a byte order mark
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+
     /// Get lines count
     pub fn lines_count(&self) -> usize {
         self.rope.lines_count()
@@ -86,6 +233,98 @@ impl Buffer {
         self.rope.line_length(line)
     }
 
+    /// Set chars words are made of for word motions; by default these are
+    /// alphanumerics and underscore. The classifier could capture its
+    /// settings, like extra word chars of a file type.
+    ///
+    /// ```
+    /// # use coredit::Buffer;
+    /// let mut buffer = Buffer::from_reader("foo-bar baz".as_bytes()).unwrap();
+    /// let extra = vec!['-'];
+    /// buffer.set_word_chars(move |c| c.is_alphanumeric() || extra.contains(&c));
+    /// buffer.move_word_forward(1, false);
+    /// assert_eq!(buffer.selections_iter().next().unwrap().from().col(), 9);
+    /// ```
+    pub fn set_word_chars<F>(&mut self, is_word_char: F)
+    where
+        F: Fn(char) -> bool + Send + Sync + 'static,
+    {
+        self.word_chars = WordChars::new(is_word_char);
+    }
+
+    /// Set pairs of opening and closing brackets to match; by default these
+    /// are `()`, `[]`, `{}` and `<>`.
+    pub fn set_bracket_pairs(&mut self, pairs: &[(char, char)]) {
+        self.bracket_pairs = pairs.to_vec();
+    }
+
+    /// Set whether char search looks for chars beyond cursors' lines; by
+    /// default it stays on the line.
+    pub fn set_find_across_lines(&mut self, across_lines: bool) {
+        self.find_across_lines = across_lines;
+    }
+
+    /// Get the line ending used by the buffer
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Get the unit lines are indented with
+    pub fn indent_unit(&self) -> IndentUnit {
+        self.indent_unit
+    }
+
+    /// Set the unit lines are indented with; by default it's 4 spaces.
+    pub fn set_indent_unit(&mut self, indent_unit: IndentUnit) {
+        self.indent_unit = indent_unit;
+    }
+
+    /// Set how lines started by line breaks inserted with `insert` are
+    /// indented; by default they are not.
+    pub fn set_auto_indent(&mut self, auto_indent: AutoIndent) {
+        self.auto_indent = auto_indent;
+    }
+
+    /// Get the tab width up/down movements keep cursors' display columns with
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Set the tab width up/down movements keep cursors' display columns
+    /// with; by default it's 4.
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+        self.layout = Layout::new(&self.rope, self.layout.wrap(), tab_width);
+    }
+
+    /// Wrap lines wider than `width` display columns into several visual
+    /// rows, breaking them as `mode` allows. Rows are kept up to date on
+    /// edits, rewrapping only affected lines.
+    pub fn set_soft_wrap(&mut self, width: usize, mode: WrapMode) {
+        self.layout = Layout::new(&self.rope, Some((width, mode)), self.tab_width);
+    }
+
+    /// Stop wrapping lines, so each one takes a single visual row
+    pub fn unset_soft_wrap(&mut self) {
+        self.layout = Layout::new(&self.rope, None, self.tab_width);
+    }
+
+    /// Get visual rows the line `line` is displayed on, or `None` if
+    /// there is no such line.
+    ///
+    /// ```
+    /// # use coredit::{Buffer, VisualRow, WrapMode};
+    /// let mut buffer = Buffer::from_reader("Hello world".as_bytes()).unwrap();
+    /// buffer.set_soft_wrap(8, WrapMode::Word);
+    /// let rows = buffer.visual_rows(1).unwrap();
+    /// assert_eq!(rows[1], VisualRow { line: 1, start: 7, end: 13 });
+    /// ```
+    pub fn visual_rows(&self, line: usize) -> Option<Vec<VisualRow>> {
+        self.rope
+            .line_length(line)
+            .map(|_| self.layout.rows(&self.rope, line))
+    }
+
     /// Expose underlying Rope read-only way
     pub fn get_rope(&self) -> &Rope {
         &self.rope
@@ -93,7 +332,56 @@ impl Buffer {
 
     /// Return an iterator over selections
     pub fn selections_iter(&self) -> impl Iterator<Item = Selection> + '_ {
-        self.selection_storage.iter().map(|s| s.binded(self))
+        let main_from = self.main_selection().selection.from;
+        self.selection_storage.iter().map(move |s| {
+            let is_main = s.from == main_from;
+            s.binded(self, is_main)
+        })
+    }
+
+    /// Get the main selection, the one primary actions like jumps or
+    /// reporting the cursor position are related to
+    pub fn main_selection(&self) -> Selection {
+        self.selection_storage
+            .main_selection()
+            .expect("Main selection must be in the storage")
+            .clone()
+            .binded(self, true)
+    }
+
+    /// Make the selection `n` positions after the main one the new main,
+    /// or before it if `n` is negative, wrapping around.
+    pub fn rotate_main(&mut self, n: isize) -> Vec<Delta> {
+        let deltas = self.selection_storage.rotate_main(n);
+        self.bind_scrolled(deltas)
+    }
+
+    /// Make the selection with `index`, counting from the buffer's
+    /// beginning, the main one.
+    pub fn set_main(&mut self, index: usize) -> Result<Vec<Delta>> {
+        let deltas = self
+            .selection_storage
+            .set_main(index)
+            .ok_or(Error::SelectionNotFound(index))?;
+        Ok(self.bind_scrolled(deltas))
+    }
+
+    /// Drop all selections except the main one.
+    pub fn keep_only_main(&mut self) -> Vec<Delta> {
+        let deltas = self.selection_storage.keep_only_main();
+        self.bind_scrolled(deltas)
+    }
+
+    /// Drop the main selection, so the nearest one before it becomes main
+    /// or the first one if there is no such selection. As the buffer can't
+    /// be left without selections, `Error::NothingSelected` is returned if
+    /// the main selection is the only one.
+    pub fn remove_main(&mut self) -> Result<Vec<Delta>> {
+        if self.selection_storage.selections_tree.len() == 1 {
+            return Err(Error::NothingSelected);
+        }
+        let deltas = self.selection_storage.remove_main();
+        Ok(self.bind_scrolled(deltas))
     }
 
     /// Create Position with required context
@@ -105,6 +393,58 @@ impl Buffer {
         .binded(self)
     }
 
+    /// Get the display column, starting from 0, where the grapheme cluster at
+    /// `position` starts: tabs reach the next multiple of `tab_width` and
+    /// wide chars take two columns.
+    ///
+    /// ```
+    /// # use coredit::Buffer;
+    /// let buffer = Buffer::from_reader("\t世界!".as_bytes()).unwrap();
+    /// assert_eq!(buffer.display_col(buffer.create_position(1, 4), 4), 8);
+    /// ```
+    pub fn display_col(&self, position: Position, tab_width: usize) -> usize {
+        display_col(&self.rope, position.line() - 1, position.col(), tab_width)
+    }
+
+    /// Get the position of the grapheme cluster of `line` displayed at the
+    /// display column `x`, counted as in `display_col`; if the line is
+    /// shorter, it's the position of its line break. Returns `None` if there
+    /// is no such line.
+    pub fn position_at_display_col(
+        &self,
+        line: usize,
+        x: usize,
+        tab_width: usize,
+    ) -> Option<Position> {
+        DisplayLines::new(&self.rope, tab_width)
+            .col_at_display_col(line, x)
+            .map(|col| self.create_position(line, col))
+    }
+
+    /// Get the viewport, if it's set
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    /// Set the viewport or unset it with `None`. The viewport is scrolled
+    /// to the main cursor right away and follows it on any subsequent
+    /// movement or edit. Columns are scrolled only if there is no soft wrap.
+    pub fn set_viewport(&mut self, viewport: Option<Viewport>) {
+        self.viewport = viewport;
+        self.scroll_to_main();
+    }
+
+    /// Return an iterator over selections intersecting lines of the
+    /// viewport, or over all selections if there is no viewport
+    pub fn visible_selections(&self) -> impl Iterator<Item = Selection> + '_ {
+        let (top_line, bottom_line) = match self.viewport {
+            Some(viewport) => (viewport.top_line, viewport.bottom_line()),
+            None => (1, usize::MAX),
+        };
+        self.selections_at(top_line)
+            .take_while(move |s| s.from().line() <= bottom_line)
+    }
+
     /// Return an iterator over selections since `line`
     pub fn selections_at(&self, line: usize) -> impl Iterator<Item = Selection> + '_ {
         let pos: SelectionIntersect = SelectionUnbound::from(PositionUnbound {
@@ -112,115 +452,1149 @@ impl Buffer {
             col: 1.into(),
         })
         .into();
+        let main_from = self.main_selection().selection.from;
         self.selection_storage
             .selections_tree
             .range(pos..)
-            .map(|si| si.0.clone().binded(self))
+            .map(move |si| si.0.clone().binded(self, si.0.from == main_from))
     }
 
     /// Swap selections' cursor position.
     pub fn swap_cursor(&mut self) -> Vec<Delta> {
-        DeltaType::bind_vec(self.selection_storage.swap_cursor(), self)
+        let deltas = self.selection_storage.swap_cursor();
+        self.bind_scrolled(deltas)
     }
 
     /// Move all cursors up by `n`, shrinking selections to length 1
     /// if `extend` is not set.
     pub fn move_up(&mut self, n: usize, extend: bool) -> Vec<Delta> {
-        DeltaType::bind_vec(self.selection_storage.move_up(n, extend, &self.rope), self)
+        let lines = DisplayLines::new(&self.rope, self.tab_width);
+        let deltas = self.selection_storage.move_up(n, extend, &lines);
+        self.bind_scrolled(deltas)
     }
 
     /// Move all cursors down by `n`, shrinking selections to length 1
     /// if `extend` is not set.
     pub fn move_down(&mut self, n: usize, extend: bool) -> Vec<Delta> {
-        DeltaType::bind_vec(
-            self.selection_storage.move_down(n, extend, &self.rope),
-            self,
-        )
+        let lines = DisplayLines::new(&self.rope, self.tab_width);
+        let deltas = self.selection_storage.move_down(n, extend, &lines);
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move all cursors up by `n` visual rows, so wrapped lines are passed
+    /// row by row, shrinking selections to length 1 if `extend` is not set.
+    /// Cursors keep their display columns counted from rows' starts.
+    pub fn move_visual_up(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_visual(-(n as isize), extend)
+    }
+
+    /// Move all cursors down by `n` visual rows, so wrapped lines are passed
+    /// row by row, shrinking selections to length 1 if `extend` is not set.
+    /// Cursors keep their display columns counted from rows' starts.
+    pub fn move_visual_down(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_visual(n as isize, extend)
+    }
+
+    /// Scroll the viewport a page down moving all cursors by its height,
+    /// shrinking selections to length 1 if `extend` is not set. Does
+    /// nothing if there is no viewport.
+    pub fn page_down(&mut self, extend: bool) -> Vec<Delta> {
+        self.scroll_pages(1, 1, extend)
+    }
+
+    /// Scroll the viewport a page up moving all cursors by its height,
+    /// shrinking selections to length 1 if `extend` is not set. Does
+    /// nothing if there is no viewport.
+    pub fn page_up(&mut self, extend: bool) -> Vec<Delta> {
+        self.scroll_pages(-1, 1, extend)
+    }
+
+    /// Scroll the viewport half a page down moving all cursors by half of
+    /// its height, shrinking selections to length 1 if `extend` is not set.
+    /// Does nothing if there is no viewport.
+    pub fn half_page_down(&mut self, extend: bool) -> Vec<Delta> {
+        self.scroll_pages(1, 2, extend)
+    }
+
+    /// Scroll the viewport half a page up moving all cursors by half of
+    /// its height, shrinking selections to length 1 if `extend` is not set.
+    /// Does nothing if there is no viewport.
+    pub fn half_page_up(&mut self, extend: bool) -> Vec<Delta> {
+        self.scroll_pages(-1, 2, extend)
     }
 
     /// Move all cursors left by `n`, shrinking selections to length 1
     /// if `extend` is not set.
     pub fn move_left(&mut self, n: usize, extend: bool) -> Vec<Delta> {
-        DeltaType::bind_vec(
-            self.selection_storage.move_left(n, extend, &self.rope),
-            self,
-        )
+        let deltas = self.selection_storage.move_left(n, extend, &self.rope);
+        self.bind_scrolled(deltas)
     }
 
     /// Move all cursors right by `n`, shrinking selections to length 1
     /// if `extend` is not set.
     pub fn move_right(&mut self, n: usize, extend: bool) -> Vec<Delta> {
-        DeltaType::bind_vec(
-            self.selection_storage.move_right(n, extend, &self.rope),
-            self,
-        )
+        let deltas = self.selection_storage.move_right(n, extend, &self.rope);
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move all cursors to the start of the `n`-th next word, like `w` in
+    /// Vim, shrinking selections to length 1 if `extend` is not set.
+    ///
+    /// A word is a sequence of word chars (see `set_word_chars`) or
+    /// a sequence of other non-blank chars. Line breaks are blanks.
+    pub fn move_word_forward(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, false, |words, rope, idx| {
+            words.next_start(rope, idx)
+        })
+    }
+
+    /// Move all cursors to the start of the `n`-th previous word, like `b`
+    /// in Vim, shrinking selections to length 1 if `extend` is not set.
+    pub fn move_word_backward(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, false, |words, rope, idx| {
+            words.prev_start(rope, idx)
+        })
+    }
+
+    /// Move all cursors to the end of the `n`-th next word, like `e` in Vim,
+    /// shrinking selections to length 1 if `extend` is not set.
+    pub fn move_word_end(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, false, |words, rope, idx| {
+            words.next_end(rope, idx)
+        })
+    }
+
+    /// Move all cursors to the start of the `n`-th next big word, like `W`
+    /// in Vim. A big word is a sequence of any non-blank chars.
+    pub fn move_big_word_forward(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, true, |words, rope, idx| {
+            words.next_start(rope, idx)
+        })
+    }
+
+    /// Move all cursors to the start of the `n`-th previous big word, like
+    /// `B` in Vim.
+    pub fn move_big_word_backward(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, true, |words, rope, idx| {
+            words.prev_start(rope, idx)
+        })
+    }
+
+    /// Move all cursors to the end of the `n`-th next big word, like `E`
+    /// in Vim.
+    pub fn move_big_word_end(&mut self, n: usize, extend: bool) -> Vec<Delta> {
+        self.move_by_words(n, extend, true, |words, rope, idx| {
+            words.next_end(rope, idx)
+        })
+    }
+
+    /// Move cursors applying a word boundary search `n` times
+    fn move_by_words(
+        &mut self,
+        n: usize,
+        extend: bool,
+        big: bool,
+        motion: fn(&Words<'_>, &Rope, usize) -> usize,
+    ) -> Vec<Delta> {
+        let word_chars = self.word_chars.clone();
+        let words = Words {
+            word_chars: &word_chars,
+            big,
+        };
+        self.move_cursors(extend, |rope, cursor| {
+            let char_idx = (0..n).fold(cursor.to_char_idx(rope), |char_idx, _| {
+                motion(&words, rope, char_idx)
+            });
+            PositionUnbound::from_char_idx(rope, char_idx)
+        })
+    }
+
+    /// Move all cursors to the beginning of their lines, shrinking
+    /// selections to length 1 if `extend` is not set.
+    pub fn move_to_line_start(&mut self, extend: bool) -> Vec<Delta> {
+        self.move_cursors(extend, |_, cursor| PositionUnbound {
+            line: cursor.line,
+            col: 1.into(),
+        })
+    }
+
+    /// Move all cursors to the first non-blank grapheme cluster of their
+    /// lines or to the line end if the line is blank, shrinking selections
+    /// to length 1 if `extend` is not set.
+    pub fn move_to_first_non_blank(&mut self, extend: bool) -> Vec<Delta> {
+        self.move_cursors(extend, |rope, cursor| {
+            PositionUnbound::from_char_idx(rope, first_non_blank(rope, cursor.line.get() - 1))
+        })
+    }
+
+    /// Move all cursors to the first non-blank grapheme cluster of their
+    /// lines; cursors which are already there are moved to the beginning of
+    /// their lines instead. Selections are shrinked to length 1 if `extend`
+    /// is not set.
+    pub fn move_smart_home(&mut self, extend: bool) -> Vec<Delta> {
+        self.move_cursors(extend, |rope, cursor| {
+            let first_non_blank =
+                PositionUnbound::from_char_idx(rope, first_non_blank(rope, cursor.line.get() - 1));
+            if cursor == first_non_blank {
+                PositionUnbound {
+                    line: cursor.line,
+                    col: 1.into(),
+                }
+            } else {
+                first_non_blank
+            }
+        })
+    }
+
+    /// Move all cursors to the ends of their lines, which are positions of
+    /// line breaks, shrinking selections to length 1 if `extend` is not set.
+    ///
+    /// Cursors stick to line ends on subsequent up/down movements.
+    pub fn move_to_line_end(&mut self, extend: bool) -> Vec<Delta> {
+        let rope = &self.rope;
+        let deltas = self
+            .selection_storage
+            .apply_to_selections(|s| s.move_to_line_end(extend, rope));
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move all cursors to the beginning of the buffer, shrinking
+    /// selections to length 1 if `extend` is not set.
+    pub fn move_to_buffer_start(&mut self, extend: bool) -> Vec<Delta> {
+        self.move_cursors(extend, |_, _| Default::default())
+    }
+
+    /// Move all cursors to the end of the buffer, shrinking selections to
+    /// length 1 if `extend` is not set.
+    pub fn move_to_buffer_end(&mut self, extend: bool) -> Vec<Delta> {
+        self.move_cursors(extend, |rope, _| {
+            PositionUnbound::from_char_idx(rope, rope.len_chars())
+        })
+    }
+
+    /// Move all cursors to the line `line` keeping their columns the same
+    /// way as up/down movements do, shrinking selections to length 1 if
+    /// `extend` is not set. Lines past the buffer's end go to the last line.
+    pub fn goto_line(&mut self, line: usize, extend: bool) -> Vec<Delta> {
+        let lines = DisplayLines::new(&self.rope, self.tab_width);
+        let line = line.clamp(1, lines.lines_count());
+        let deltas = self
+            .selection_storage
+            .apply_to_selections(|s| s.move_to_line(line, extend, lines));
+        self.bind_scrolled(deltas)
+    }
+
+    /// Find a position of the bracket paired with one at `position`
+    /// skipping nested pairs. Returns `None` if there is no bracket at
+    /// `position` or it has no pair.
+    pub fn matching_bracket(&self, position: Position) -> Option<Position> {
+        let char_idx = position.position.to_char_idx(&self.rope);
+        matching_bracket(&self.rope, char_idx, &self.bracket_pairs)
+            .map(|idx| PositionUnbound::from_char_idx(&self.rope, idx).binded(self))
+    }
+
+    /// Move all cursors which are on brackets to their pairs, shrinking
+    /// selections to length 1 if `extend` is not set. Other cursors stay
+    /// in place.
+    pub fn goto_matching_bracket(&mut self, extend: bool) -> Vec<Delta> {
+        let pairs = &self.bracket_pairs;
+        let rope = &self.rope;
+        let deltas = self.selection_storage.move_cursors(extend, |cursor| {
+            matching_bracket(rope, cursor.to_char_idx(rope), pairs)
+                .map(|idx| PositionUnbound::from_char_idx(rope, idx))
+                .unwrap_or(cursor)
+        });
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move all cursors to the `n`-th occurrence of `c` in `direction`, like
+    /// `f`/`F` in Vim, or next to it if not `inclusive`, like `t`/`T`.
+    /// Cursors with not enough occurrences stay in place. Selections are
+    /// shrinked to length 1 if `extend` is not set.
+    ///
+    /// The search is remembered to be repeated with `repeat_find`.
+    pub fn find_char(
+        &mut self,
+        c: char,
+        direction: CursorDirection,
+        inclusive: bool,
+        n: usize,
+        extend: bool,
+    ) -> Vec<Delta> {
+        let search = CharSearch {
+            c,
+            direction,
+            inclusive,
+        };
+        self.last_find = Some(search);
+        self.move_to_char(search, n, extend, false)
+    }
+
+    /// Repeat the last `find_char` search `n` times, in the opposite
+    /// direction if `reverse` is set, like `;`/`,` in Vim. Does nothing if
+    /// there was no search.
+    pub fn repeat_find(&mut self, n: usize, reverse: bool, extend: bool) -> Vec<Delta> {
+        match self.last_find {
+            Some(mut search) => {
+                if reverse {
+                    search.direction = match search.direction {
+                        CursorDirection::Forward => CursorDirection::Backward,
+                        CursorDirection::Backward => CursorDirection::Forward,
+                    };
+                }
+                self.move_to_char(search, n, extend, true)
+            }
+            None => vec![],
+        }
+    }
+
+    /// Move cursors according to a char search; on `repeat` a cursor next to
+    /// the char being searched up to is not stuck as the search starts
+    /// behind it.
+    fn move_to_char(
+        &mut self,
+        search: CharSearch,
+        n: usize,
+        extend: bool,
+        repeat: bool,
+    ) -> Vec<Delta> {
+        let across_lines = self.find_across_lines;
+        self.move_cursors(extend, |rope, cursor| {
+            let char_idx = cursor.to_char_idx(rope);
+            let bounds = if across_lines {
+                0..rope.len_chars()
+            } else {
+                let line_idx = cursor.line.get() - 1;
+                rope.line_to_char(line_idx)..line_content_end(rope, line_idx)
+            };
+            let found = match (search.direction, search.inclusive) {
+                (CursorDirection::Forward, true) => {
+                    find_char(rope, char_idx, search.c, true, n, bounds)
+                }
+                (CursorDirection::Forward, false) => {
+                    let from = if repeat {
+                        next_grapheme_boundary(rope, char_idx)
+                    } else {
+                        char_idx
+                    };
+                    find_char(rope, from, search.c, true, n, bounds)
+                        .map(|idx| prev_grapheme_boundary(rope, idx))
+                }
+                (CursorDirection::Backward, true) => {
+                    find_char(rope, char_idx, search.c, false, n, bounds)
+                }
+                (CursorDirection::Backward, false) => {
+                    let from = if repeat {
+                        prev_grapheme_boundary(rope, char_idx)
+                    } else {
+                        char_idx
+                    };
+                    find_char(rope, from, search.c, false, n, bounds)
+                        .map(|idx| next_grapheme_boundary(rope, idx))
+                }
+            };
+            found
+                .map(|idx| PositionUnbound::from_char_idx(rope, idx))
+                .unwrap_or(cursor)
+        })
+    }
+
+    /// Select a text object for each selection keeping its cursor
+    /// direction; selections which have no such object stay as they are.
+    /// Overlapping selections are merged.
+    ///
+    /// ```
+    /// # use coredit::{Buffer, ObjectScope, TextObject};
+    /// let mut buffer = Buffer::from_reader("(a, b)".as_bytes()).unwrap();
+    /// buffer.select_object(TextObject::Brackets('(', ')'), ObjectScope::Inner);
+    /// let selection = buffer.selections_iter().next().unwrap();
+    /// assert_eq!((selection.from().col(), selection.to().col()), (2, 5));
+    /// ```
+    pub fn select_object(&mut self, object: TextObject, scope: ObjectScope) -> Vec<Delta> {
+        let words = Words {
+            word_chars: &self.word_chars,
+            big: false,
+        };
+        let rope = &self.rope;
+        let deltas = self.selection_storage.apply_to_selections(|s| {
+            let cursor = s.get_cursor().to_char_idx(rope);
+            match object.find(rope, scope, s.to_char_range(rope), cursor, words) {
+                Some(range) => SelectionUnbound::from_char_range(rope, range, s.cursor_direction),
+                None => s,
+            }
+        });
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move cursors to positions found from current ones by `f`
+    fn move_cursors<F>(&mut self, extend: bool, f: F) -> Vec<Delta>
+    where
+        F: Fn(&Rope, PositionUnbound) -> PositionUnbound,
+    {
+        let rope = &self.rope;
+        let deltas = self
+            .selection_storage
+            .move_cursors(extend, |cursor| f(rope, cursor));
+        self.bind_scrolled(deltas)
+    }
+
+    /// Move all cursors by `n` visual rows, up if it is negative. A column
+    /// which didn't fit the line is kept as sticky like up/down movements do.
+    fn move_visual(&mut self, n: isize, extend: bool) -> Vec<Delta> {
+        let rope = &self.rope;
+        let layout = &self.layout;
+        let tab_width = self.tab_width;
+        let deltas = self.selection_storage.apply_to_selections(|s| {
+            let cursor = *s.get_cursor();
+            let (x, sticky) = match s.sticky_column {
+                Some(sticky_column) => (sticky_column.get() - 1, true),
+                None => (
+                    display_col(rope, cursor.line.get() - 1, cursor.col.get(), tab_width),
+                    false,
+                ),
+            };
+            let (position, missed) = layout.move_rows(rope, cursor, x, sticky, n);
+            let mut s = s.move_to(position, extend);
+            s.sticky_column = missed.map(|x| x.saturating_add(1).into());
+            s
+        });
+        self.bind_scrolled(deltas)
+    }
+
+    /// Scroll the viewport and move cursors by `1 / divisor` of its height,
+    /// down if `direction` is positive or up otherwise
+    fn scroll_pages(&mut self, direction: isize, divisor: usize, extend: bool) -> Vec<Delta> {
+        let lines_count = self.lines_count();
+        let viewport = match &mut self.viewport {
+            Some(viewport) => viewport,
+            None => return vec![],
+        };
+        let n = (viewport.height / divisor).max(1);
+        let last_page_top = (lines_count + 1).saturating_sub(viewport.height).max(1);
+        viewport.top_line = if direction > 0 {
+            (viewport.top_line + n)
+                .min(last_page_top)
+                .max(viewport.top_line)
+        } else {
+            viewport.top_line.saturating_sub(n).max(1)
+        };
+        if direction > 0 {
+            self.move_down(n, extend)
+        } else {
+            self.move_up(n, extend)
+        }
+    }
+
+    /// Scroll the viewport to the main cursor if there is a viewport
+    fn scroll_to_main(&mut self) {
+        let viewport = match &mut self.viewport {
+            Some(viewport) => viewport,
+            None => return,
+        };
+        let lines_count = self.rope.lines_count();
+        let cursor = *self
+            .selection_storage
+            .main_selection()
+            .expect("Main selection must be in the storage")
+            .get_cursor();
+        viewport.scroll_to_line(cursor.line.get(), lines_count);
+        if self.layout.wrap().is_none() {
+            let x = display_col(
+                &self.rope,
+                cursor.line.get() - 1,
+                cursor.col.get(),
+                self.tab_width,
+            );
+            viewport.scroll_to_col(x);
+        } else {
+            viewport.left_col = 0;
+        }
+    }
+
+    /// Bind deltas of a movement scrolling the viewport to the main cursor
+    fn bind_scrolled<'b>(&mut self, deltas: Vec<DeltaType<'b>>) -> Vec<Delta<'_, 'b>> {
+        self.scroll_to_main();
+        DeltaType::bind_vec(deltas, self)
     }
 
     /// Place a new selection under each existing one with the same columns if it will fit the line.
     /// If the next line is too short to put a selection then it will use matching subsequent line.
     pub fn place_selection_under(&mut self) -> Vec<Delta> {
-        DeltaType::bind_vec(
-            self.selection_storage.place_selection_under(&self.rope),
-            self,
-        )
+        let deltas = self.selection_storage.place_selection_under(&self.rope);
+        self.bind_scrolled(deltas)
     }
 
     /// Insert `text` on all cursors.
     ///
+    /// Cursors are advanced by grapheme clusters of `text`, so a cursor
+    /// is never placed in the middle of a user-perceived character.
+    ///
     /// If selection's cursor is in front, then the selection will be moved
     /// right; otherwise it will be extened.
-    pub fn insert(&mut self, text: &str) {
-        // Perform insertion reversed to prevent selections invalidation
-        // on previous iteration if it were moved forward
-        for s in self.selection_storage.iter().rev() {
-            let cursor = s.get_cursor();
-            let ch: usize = self.rope.line_to_char(cursor.line.get() - 1) + cursor.col.get() - 1;
-            self.rope.insert(ch, text);
+    ///
+    /// With auto-indent set (see `set_auto_indent`), lines started by line
+    /// breaks of `text` are indented on each cursor on its own, and cursors
+    /// land after the indentation.
+    pub fn insert(&mut self, text: &str) -> Vec<Delta> {
+        if text.is_empty() {
+            return vec![];
+        }
+        if self.auto_indent == AutoIndent::Off || !text.contains('\n') {
+            return self.insert_each(std::iter::repeat(text.to_owned()));
         }
+        let texts: Vec<String> = self
+            .selection_storage
+            .iter()
+            .map(|s| {
+                let cursor = s.get_cursor().to_char_idx(&self.rope);
+                let line_start = self.rope.line_to_char(self.rope.char_to_line(cursor));
+                let line_prefix = self.rope.slice(line_start..cursor).to_string();
+                auto_indent(
+                    text,
+                    &line_prefix,
+                    self.auto_indent,
+                    self.indent_unit,
+                    |c| self.bracket_pairs.iter().any(|&(open, _)| open == c),
+                )
+            })
+            .collect();
+        self.insert_each(texts)
+    }
 
-        // TODO: fix to grapheme clusters
-        let insertion_info = text.chars().group_by(|&x| x == '\n');
-        for (is_nl, group) in &insertion_info {
-            let l = group.count();
-            if is_nl {
-                self.selection_storage.move_down_incremental(l);
-            } else {
-                self.selection_storage.move_right_incremental(l);
-            }
+    /// Insert its own text on each cursor, the first text goes to the
+    /// first selection in the buffer and so on. Selections left without
+    /// text stay as they are.
+    ///
+    /// Cursors are moved the same way as `insert` does.
+    pub fn insert_each(&mut self, texts: impl IntoIterator<Item = String>) -> Vec<Delta> {
+        let mut changes = ChangeSet::new();
+        let mut char_idx = 0;
+        for (s, text) in self.selection_storage.iter().zip(texts) {
+            let cursor = s.get_cursor().to_char_idx(&self.rope);
+            changes.retain(cursor - char_idx);
+            changes.insert(&text);
+            char_idx = cursor;
         }
+        changes.retain(self.rope.len_chars() - char_idx);
+
+        let selections_before = self.selection_storage.clone();
+        self.change(changes, Assoc::After, selections_before)
+    }
+
+    /// Replace selected text with `text` for all selections. Each selection
+    /// covers the new text afterwards keeping its cursor direction; for
+    /// empty `text` selections shrink to length 1.
+    ///
+    /// ```
+    /// # use coredit::Buffer;
+    /// let mut buffer = Buffer::from_reader("old text".as_bytes()).unwrap();
+    /// buffer.replace("new");
+    /// assert_eq!(buffer.to_string(), "newld text");
+    /// ```
+    pub fn replace(&mut self, text: &str) -> Vec<Delta> {
+        self.replace_each(std::iter::repeat(text.to_owned()))
+    }
+
+    /// Replace selected text with its own text for each selection and
+    /// select it, the first text goes to the first selection in the buffer
+    /// and so on. Selections left without text stay as they are.
+    pub fn replace_each(&mut self, texts: impl IntoIterator<Item = String>) -> Vec<Delta> {
+        let rope = &self.rope;
+        let replacements = self
+            .selection_storage
+            .iter()
+            .zip(texts.into_iter().map(Some).chain(std::iter::repeat(None)))
+            .map(|(s, text)| text.map(|text| (s.to_char_range(rope), text)))
+            .collect();
+        self.replace_ranges(replacements)
     }
 
     /// Delete selected text for all selections.
     ///
     /// All selections will have length equal 1.
-    pub fn delete(&mut self) {
-        let mut current_selection = self.selection_storage.iter().rev().next();
-
-        while let Some(s) = current_selection.take() {
-            let (from, to) = s.get_bounds();
-            let from_line: usize = from.line.into();
-            let to_line: usize = to.line.into();
-
-            let from_ch: usize = self.rope.line_to_char(from_line - 1) + from.col.get() - 1;
-            let to_ch: usize = {
-                if to.col.get()
-                    == self
-                        .rope
-                        .line_length(to_line)
-                        .expect("Selection reached inconsistency")
+    pub fn delete(&mut self) -> Vec<Delta> {
+        let mut changes = ChangeSet::new();
+        let mut char_idx = 0;
+        for s in self.selection_storage.iter() {
+            // The whole grapheme cluster under `to` goes away too,
+            // including a line break
+            let range = s.to_char_range(&self.rope);
+            changes.retain(range.start - char_idx);
+            changes.delete(range.len());
+            char_idx = range.end;
+        }
+        changes.retain(self.rope.len_chars() - char_idx);
+
+        let selections_before = self.selection_storage.clone();
+        self.selection_storage.drop_selections_to_head();
+        self.change(changes, Assoc::After, selections_before)
+    }
+
+    /// Store contents of selections in the register `name`, one value per
+    /// selection.
+    pub fn yank(&self, registers: &mut Registers, name: char) {
+        let values = self
+            .selection_storage
+            .iter()
+            .map(|s| self.rope.slice(s.to_char_range(&self.rope)).to_string())
+            .collect();
+        registers.set(name, values);
+    }
+
+    /// Insert values of the register `name` before selections and select
+    /// them, like `P` in Kakoune. See `paste_replace` on how values are
+    /// distributed.
+    pub fn paste_before(&mut self, registers: &Registers, name: char) -> Vec<Delta> {
+        self.paste(registers, name, |range| range.start..range.start)
+    }
+
+    /// Insert values of the register `name` after selections and select
+    /// them, like `p` in Kakoune. See `paste_replace` on how values are
+    /// distributed.
+    pub fn paste_after(&mut self, registers: &Registers, name: char) -> Vec<Delta> {
+        self.paste(registers, name, |range| range.end..range.end)
+    }
+
+    /// Replace selected text with values of the register `name` and select
+    /// them, like `R` in Kakoune.
+    ///
+    /// If there are as many values as selections, each selection gets its
+    /// own value; otherwise each one gets all values joined with the
+    /// buffer's line ending. Nothing happens for an empty register.
+    pub fn paste_replace(&mut self, registers: &Registers, name: char) -> Vec<Delta> {
+        self.paste(registers, name, |range| range)
+    }
+
+    /// Add `n` indentation units to the beginning of each line touched by
+    /// selections; empty lines are left as they are. Selections stay on
+    /// the same text.
+    pub fn indent(&mut self, n: usize) -> Vec<Delta> {
+        let indentation = self.indent_unit.repeat(n);
+        self.change_lines(|rope, line_idx| {
+            if line_content_end(rope, line_idx) > rope.line_to_char(line_idx) {
+                (indentation.clone(), 0)
+            } else {
+                (String::new(), 0)
+            }
+        })
+    }
+
+    /// Remove up to `n` indentation units from the beginning of each line
+    /// touched by selections. Selections stay on the same text.
+    pub fn dedent(&mut self, n: usize) -> Vec<Delta> {
+        let indent_unit = self.indent_unit;
+        self.change_lines(|rope, line_idx| {
+            (String::new(), indent_unit.leading_len(rope, line_idx, n))
+        })
+    }
+
+    /// Replace chars at the beginning of each line touched by selections,
+    /// once per line; `f` gives the text to insert and the count of chars
+    /// to delete
+    fn change_lines<F>(&mut self, f: F) -> Vec<Delta>
+    where
+        F: Fn(&Rope, usize) -> (String, usize),
+    {
+        let lines: BTreeSet<usize> = self
+            .selection_storage
+            .iter()
+            .flat_map(|s| s.from.line.get() - 1..s.to.line.get())
+            .collect();
+        let mut changes = ChangeSet::new();
+        let mut char_idx = 0;
+        for line_idx in lines {
+            let line_start = self.rope.line_to_char(line_idx);
+            let (text, delete) = f(&self.rope, line_idx);
+            changes.retain(line_start - char_idx);
+            changes.insert(&text);
+            changes.delete(delete);
+            char_idx = line_start + delete;
+        }
+        changes.retain(self.rope.len_chars() - char_idx);
+
+        let selections_before = self.selection_storage.clone();
+        self.change(changes, Assoc::After, selections_before)
+    }
+
+    /// Replace chars found by `target` for each selection's range with
+    /// register values, selecting inserted text
+    fn paste<F>(&mut self, registers: &Registers, name: char, target: F) -> Vec<Delta>
+    where
+        F: Fn(Range<usize>) -> Range<usize>,
+    {
+        let values = match registers.get(name) {
+            Some(values) if !values.is_empty() => values,
+            _ => return vec![],
+        };
+        let selections_count = self.selection_storage.selections_tree.len();
+        let values: Vec<String> = if values.len() == selections_count {
+            values.to_vec()
+        } else {
+            vec![values.join(self.line_ending.as_str()); selections_count]
+        };
+        let rope = &self.rope;
+        let replacements = self
+            .selection_storage
+            .iter()
+            .zip(values)
+            .map(|(s, value)| Some((target(s.to_char_range(rope)), value)))
+            .collect();
+        self.replace_ranges(replacements)
+    }
+
+    /// Replace chars ranges with texts, one replacement per selection in
+    /// order, and select inserted texts; selections without replacement
+    /// are kept
+    fn replace_ranges(&mut self, replacements: Vec<Option<(Range<usize>, String)>>) -> Vec<Delta> {
+        let mut changes = ChangeSet::new();
+        let mut selections = vec![];
+        let mut char_idx = 0;
+        let mut shift = 0isize;
+        for (s, replacement) in self.selection_storage.iter().zip(replacements) {
+            let shifted = |idx: usize| (idx as isize + shift) as usize;
+            match replacement {
+                Some((range, text)) => {
+                    changes.retain(range.start - char_idx);
+                    changes.insert(&text);
+                    changes.delete(range.len());
+                    char_idx = range.end;
+
+                    let start = shifted(range.start);
+                    let len = text.chars().count();
+                    selections.push((start..start + len.max(1), s.cursor_direction));
+                    shift += len as isize - range.len() as isize;
+                }
+                None => {
+                    let range = s.to_char_range(&self.rope);
+                    let start = shifted(range.start);
+                    selections.push((start..start + range.len().max(1), s.cursor_direction));
+                }
+            }
+        }
+        changes.retain(self.rope.len_chars() - char_idx);
+
+        let selections_before = self.selection_storage.clone();
+        self.change_selecting(changes, selections_before, selections)
+    }
+
+    /// Replace each selection with matches of `regex` found inside it.
+    /// The last match becomes the main selection.
+    ///
+    /// Selections without matches are dropped; if nothing matches at all,
+    /// selections are kept as they are and `Error::NothingSelected` is
+    /// returned.
+    pub fn select_matches(&mut self, regex: &Regex) -> Result<Vec<Delta>> {
+        let matches: Vec<SelectionUnbound> = self
+            .selection_storage
+            .iter()
+            .flat_map(|s| find_matches(&self.rope, regex, s.to_char_range(&self.rope)))
+            .map(|range| {
+                SelectionUnbound::from_char_range(&self.rope, range, CursorDirection::Forward)
+            })
+            .collect();
+        if matches.is_empty() {
+            return Err(Error::NothingSelected);
+        }
+        let deltas = self.selection_storage.replace_all(matches);
+        Ok(self.bind_scrolled(deltas))
+    }
+
+    /// Split each selection into pieces between matches of `regex`,
+    /// keeping the selection's cursor direction. The last piece becomes
+    /// the main selection.
+    ///
+    /// Selections consisting of matches only are dropped; if no pieces
+    /// are left at all, selections are kept as they are and
+    /// `Error::NothingSelected` is returned.
+    pub fn split_selections(&mut self, regex: &Regex) -> Result<Vec<Delta>> {
+        let pieces = self.split_each(|rope, range| split_by_matches(rope, regex, range), false);
+        if pieces.is_empty() {
+            return Err(Error::NothingSelected);
+        }
+        let deltas = self.selection_storage.replace_all(pieces);
+        Ok(self.bind_scrolled(deltas))
+    }
+
+    /// Split each multiline selection into selections within one line
+    /// each, including line breaks. The last piece becomes the main
+    /// selection. Selections with no chars, like a cursor at the end of the
+    /// text, are kept as they are.
+    pub fn split_lines(&mut self) -> Vec<Delta> {
+        let pieces = self.split_each(split_by_lines, true);
+        let deltas = self.selection_storage.replace_all(pieces);
+        self.bind_scrolled(deltas)
+    }
+
+    /// Keep only selections which contain a match of `regex`, or only
+    /// ones which don't contain it if `invert` is set.
+    ///
+    /// If the main selection is dropped, the nearest one before it becomes
+    /// main. If no selection is left, selections are kept as they are and
+    /// `Error::NothingSelected` is returned.
+    pub fn keep_selections_matching(&mut self, regex: &Regex, invert: bool) -> Result<Vec<Delta>> {
+        let rope = &self.rope;
+        let keep: Vec<bool> = self
+            .selection_storage
+            .iter()
+            .map(|s| regex.is_match(&Cow::from(rope.slice(s.to_char_range(rope)))) != invert)
+            .collect();
+        if !keep.contains(&true) {
+            return Err(Error::NothingSelected);
+        }
+        // Selections are visited in the same order as by `iter`
+        let mut keep = keep.into_iter();
+        let deltas = self
+            .selection_storage
+            .retain(|_| keep.next().unwrap_or_default());
+        Ok(self.bind_scrolled(deltas))
+    }
+
+    /// Build selections from pieces of chars each selection is split into,
+    /// keeping its cursor direction. Selections with no pieces are dropped
+    /// unless `keep_unsplit` is set.
+    fn split_each<F>(&self, split: F, keep_unsplit: bool) -> Vec<SelectionUnbound>
+    where
+        F: Fn(&Rope, Range<usize>) -> Vec<Range<usize>>,
+    {
+        self.selection_storage
+            .iter()
+            .flat_map(|s| {
+                let pieces = split(&self.rope, s.to_char_range(&self.rope));
+                if pieces.is_empty() && keep_unsplit {
+                    return vec![s];
+                }
+                pieces
+                    .into_iter()
+                    .map(|range| {
+                        SelectionUnbound::from_char_range(&self.rope, range, s.cursor_direction)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Replace all line endings with `line_ending` and use it as the
+    /// buffer's one from now on.
+    ///
+    /// As a line break takes one position regardless of its length,
+    /// selections stay on the same positions. Vertical tabs and form
+    /// feeds are not line endings and are kept.
+    pub fn convert_line_endings(&mut self, line_ending: LineEnding) -> Vec<Delta> {
+        let mut changes = ChangeSet::new();
+        let mut char_idx = 0;
+        for line_idx in 0..self.rope.len_lines() {
+            let line_break = line_break(&self.rope, line_idx);
+            match LineEnding::from_line_break(&line_break.to_string()) {
+                Some(ending) if ending != line_ending => {
+                    let line_end = line_content_end(&self.rope, line_idx);
+                    changes.retain(line_end - char_idx);
+                    changes.insert(line_ending.as_str());
+                    changes.delete(line_break.len_chars());
+                    char_idx = line_end + line_break.len_chars();
+                }
+                _ => {}
+            }
+        }
+        changes.retain(self.rope.len_chars() - char_idx);
+        self.line_ending = line_ending;
+
+        // Selections stay in front of new line breaks
+        let selections_before = self.selection_storage.clone();
+        self.change(changes, Assoc::Before, selections_before)
+    }
+
+    /// Run several operations as one change: it will be undone and redone
+    /// at once and deltas of all operations are coalesced into a single
+    /// list.
+    ///
+    /// If `f` returns an error, text and selections are rolled back to the
+    /// state they had before the transaction. Transactions could be nested,
+    /// then the inner one becomes a part of the outer one.
+    ///
+    /// ```
+    /// # use coredit::Buffer;
+    /// let mut buffer = Buffer::from_reader("Hello".as_bytes()).unwrap();
+    /// let result: Result<_, ()> = buffer.transaction(|buffer| {
+    ///     buffer.move_right(5, false);
+    ///     buffer.insert(", world");
+    ///     Err(())
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(buffer.to_string(), "Hello".to_string());
+    /// ```
+    pub fn transaction<F, E>(&mut self, f: F) -> std::result::Result<Vec<Delta>, E>
+    where
+        F: FnOnce(&mut Buffer) -> std::result::Result<(), E>,
+    {
+        let selections_before = self.selection_storage.clone();
+        self.history.begin_group(
+            self.rope.len_chars(),
+            selections_before.clone(),
+            self.line_ending,
+        );
+        self.transactions.push(LineChanges::new());
+        let result = f(self);
+        let line_changes = self.transactions.pop().unwrap_or_default();
+        match result {
+            Ok(()) => {
+                if let Some(changes) = self
+                    .history
+                    .end_group(self.selection_storage.clone(), self.line_ending)
                 {
-                    self.rope.line_to_char(to_line).saturating_sub(1)
-                } else {
-                    self.rope.line_to_char(to_line - 1) + to.col.get() - 1
+                    self.last_change = changes;
+                }
+                let selection_deltas = self.selection_storage.diff(&selections_before);
+                Ok(self.change_deltas(line_changes, selection_deltas))
+            }
+            Err(e) => {
+                if let Some(group) = self.history.abort_group() {
+                    let mut line_changes = LineChanges::new();
+                    line_changes.apply(&mut self.rope, &group.inverse);
+                    self.update_layout(&line_changes);
+                    self.last_change = ChangeSet::identity(self.rope.len_chars());
+                    self.line_ending = group.line_ending_before;
+                }
+                self.selection_storage = selections_before;
+                Err(e)
+            }
+        }
+    }
+
+    /// Revert the last change, restoring selections to the state they
+    /// had before it.
+    ///
+    /// This and other moves across history do nothing within a
+    /// transaction.
+    pub fn undo(&mut self) -> Vec<Delta> {
+        self.travel_with(|history| history.undo())
+    }
+
+    /// Apply again the last reverted change, restoring selections to the
+    /// state they had after it.
+    ///
+    /// If there are several branches of history, the most recently
+    /// visited one is followed.
+    pub fn redo(&mut self) -> Vec<Delta> {
+        self.travel_with(|history| history.redo())
+    }
+
+    /// Get an id of the revision the buffer is at.
+    /// The initial state of the buffer has id 0 and each change gets the
+    /// next id in order of creation.
+    pub fn current_revision(&self) -> usize {
+        self.history.current()
+    }
+
+    /// Get ids of revisions which are tips of undo tree branches
+    /// in order of creation.
+    pub fn branches(&self) -> Vec<usize> {
+        self.history.leaves()
+    }
+
+    /// Move the buffer into a state of a revision with id `revision`,
+    /// undoing and redoing changes on the path through the undo tree.
+    pub fn goto_revision(&mut self, revision: usize) -> Result<Vec<Delta>> {
+        if !self.history.contains(revision) {
+            return Err(Error::RevisionNotFound(revision));
+        }
+        Ok(self.travel_with(|history| history.goto(revision)))
+    }
+
+    /// Move `n` revisions back in order of their creation regardless
+    /// of undo tree branches.
+    pub fn earlier(&mut self, n: usize) -> Vec<Delta> {
+        self.travel_with(|history| history.earlier(n))
+    }
+
+    /// Move `n` revisions forward in order of their creation regardless
+    /// of undo tree branches.
+    pub fn later(&mut self, n: usize) -> Vec<Delta> {
+        self.travel_with(|history| history.later(n))
+    }
+
+    /// Move into the state the buffer had `duration` before the current
+    /// revision was made.
+    pub fn earlier_by(&mut self, duration: Duration) -> Vec<Delta> {
+        self.travel_with(|history| history.earlier_by(duration))
+    }
+
+    /// Move into the state the buffer had `duration` after the current
+    /// revision was made.
+    pub fn later_by(&mut self, duration: Duration) -> Vec<Delta> {
+        self.travel_with(|history| history.later_by(duration))
+    }
+
+    /// Get changes of the text made by the last operation which changed
+    /// it: an edit, a transaction or a move across history. Changes could
+    /// be used to replay the operation on another copy of the text.
+    pub fn last_change(&self) -> &ChangeSet {
+        &self.last_change
+    }
+
+    /// Move across history with `f` unless a transaction is open: its changes
+    /// are not committed yet, so history is not traveled and no deltas are
+    /// returned
+    fn travel_with<F>(&mut self, f: F) -> Vec<Delta>
+    where
+        F: FnOnce(&mut History) -> Vec<Step>,
+    {
+        if !self.transactions.is_empty() {
+            return vec![];
+        }
+        let steps = f(&mut self.history);
+        self.travel(steps)
+    }
+
+    /// Revert and apply revisions, restoring selections of the final state
+    fn travel(&mut self, steps: Vec<Step>) -> Vec<Delta> {
+        let mut line_changes = LineChanges::new();
+        let mut changes = ChangeSet::identity(self.rope.len_chars());
+        let mut selections = None;
+        let mut line_ending = self.line_ending;
+        for step in steps {
+            let (step_changes, step_selections) = match step {
+                Step::Undo(idx) => {
+                    line_ending = self.history.line_ending_before(idx);
+                    let revision = self.history.revision(idx);
+                    (&revision.inverse, &revision.selections_before)
+                }
+                Step::Redo(idx) => {
+                    line_ending = self.history.line_ending_after(idx);
+                    let revision = self.history.revision(idx);
+                    (&revision.changes, &revision.selections_after)
                 }
             };
+            line_changes.apply(&mut self.rope, step_changes);
+            changes = changes.compose(step_changes);
+            selections = Some(step_selections);
+        }
+        let selections = selections.cloned();
+        self.line_ending = line_ending;
+        self.update_layout(&line_changes);
+        let selection_deltas = match selections {
+            Some(selections) => {
+                self.last_change = changes;
+                self.selection_storage.restore(selections)
+            }
+            None => vec![],
+        };
+        self.change_deltas(line_changes, selection_deltas)
+    }
 
-            current_selection = self.selection_storage.get_first_before(&s);
-            self.selection_storage.apply_delete(s, &self.rope);
-            if to_ch < self.rope.len_chars() {
-                self.rope.remove(from_ch..=to_ch);
+    /// Apply changes made on selections `selections_before` to the text,
+    /// moving current selections along, and record them in history
+    fn change(
+        &mut self,
+        changes: ChangeSet,
+        assoc: Assoc,
+        selections_before: SelectionStorage,
+    ) -> Vec<Delta> {
+        let rope_before = self.rope.clone();
+        let mut line_changes = LineChanges::new();
+        line_changes.apply(&mut self.rope, &changes);
+        self.update_layout(&line_changes);
+        self.selection_storage
+            .map_through(&changes, assoc, &rope_before, &self.rope);
+
+        let selection_deltas =
+            self.tracked_selection_deltas(&selections_before, &changes, assoc, &rope_before);
+        let inverse = changes.invert(&rope_before);
+        self.history.commit(
+            changes.clone(),
+            inverse,
+            selections_before,
+            self.selection_storage.clone(),
+            self.line_ending,
+        );
+        self.last_change = changes;
+        self.change_deltas(line_changes, selection_deltas)
+    }
+
+    /// Apply changes made on selections `selections_before` to the text,
+    /// replacing selections with chars ranges of the changed text, and
+    /// record them in history. The main selection keeps its index.
+    fn change_selecting(
+        &mut self,
+        changes: ChangeSet,
+        selections_before: SelectionStorage,
+        selections: Vec<(Range<usize>, CursorDirection)>,
+    ) -> Vec<Delta> {
+        let rope_before = self.rope.clone();
+        let mut line_changes = LineChanges::new();
+        line_changes.apply(&mut self.rope, &changes);
+        self.update_layout(&line_changes);
+        let selections = selections
+            .into_iter()
+            .map(|(range, direction)| {
+                SelectionUnbound::from_char_range(&self.rope, range, direction)
+            })
+            .collect();
+        let selection_deltas = self
+            .selection_storage
+            .replace_with_main(selections, selections_before.main_index());
+
+        let inverse = changes.invert(&rope_before);
+        self.history.commit(
+            changes.clone(),
+            inverse,
+            selections_before,
+            self.selection_storage.clone(),
+            self.line_ending,
+        );
+        self.last_change = changes;
+        self.change_deltas(line_changes, selection_deltas)
+    }
+
+    /// Describe what became of selections after changes: a selection turned
+    /// into the one which covers its mapped head now, others merged into
+    /// the same selection are deleted.
+    fn tracked_selection_deltas<'b>(
+        &self,
+        selections_before: &SelectionStorage,
+        changes: &ChangeSet,
+        assoc: Assoc,
+        rope_before: &Rope,
+    ) -> Vec<DeltaType<'b>> {
+        let mut deltas = vec![];
+        let mut last_hit: Option<PositionUnbound> = None;
+        for old in selections_before.iter() {
+            let head = changes.map_position(old.from, assoc, rope_before, &self.rope);
+            match self.selection_storage.find_hit(head) {
+                Some(new) if last_hit != Some(new.from) => {
+                    last_hit = Some(new.from);
+                    if *new != old {
+                        deltas.push(DeltaType::SelectionChanged {
+                            identity: old.from,
+                            new_state: new.clone(),
+                        });
+                    }
+                }
+                _ => deltas.push(DeltaType::SelectionDeleted { identity: old.from }),
             }
         }
+        deltas
+    }
+
+    /// Rewrap lines affected by edits
+    fn update_layout(&mut self, line_changes: &LineChanges) {
+        let deltas = line_changes.clone().into_deltas(&self.rope);
+        self.layout.update(&self.rope, &deltas);
+    }
+
+    /// Build deltas of a change: affected lines followed by selections.
+    /// Affected lines are also collected for an open transaction.
+    fn change_deltas<'a>(
+        &'a mut self,
+        line_changes: LineChanges,
+        selection_deltas: Vec<DeltaType<'a>>,
+    ) -> Vec<Delta<'a, 'a>> {
+        self.scroll_to_main();
+        if let Some(transaction) = self.transactions.last_mut() {
+            transaction.merge(line_changes.clone());
+        }
+        let mut deltas = line_changes.into_deltas(&self.rope);
+        deltas.extend(selection_deltas);
+        DeltaType::bind_vec(deltas, self)
     }
 
     // Helper functions for testing
@@ -233,7 +1607,11 @@ impl Buffer {
 
     #[cfg(test)]
     fn insert_for_test(&mut self, line: usize, col: usize, text: &str) {
-        let ch = self.rope.line_to_char(line - 1) + col - 1;
+        let ch = PositionUnbound {
+            line: line.into(),
+            col: col.into(),
+        }
+        .to_char_idx(&self.rope);
         self.rope.insert(ch, text);
     }
 
@@ -245,28 +1623,65 @@ impl Buffer {
         to_line: usize,
         to_col: usize,
     ) {
-        let ch_from = self.rope.line_to_char(from_line - 1) + from_col - 1;
-        let ch_to: usize = {
-            let to_line_length = self.rope.line_length(to_line);
-            if to_col == to_line_length.unwrap() {
-                self.rope.line_to_char(to_line) - 1
-            } else {
-                self.rope.line_to_char(to_line - 1) + to_col - 1
-            }
-        };
-        self.rope.remove(ch_from..=ch_to);
+        let ch_from = PositionUnbound {
+            line: from_line.into(),
+            col: from_col.into(),
+        }
+        .to_char_idx(&self.rope);
+        let ch_to = PositionUnbound {
+            line: to_line.into(),
+            col: to_col.into(),
+        }
+        .to_char_idx(&self.rope);
+        self.rope
+            .remove(ch_from..crate::text::next_grapheme_boundary(&self.rope, ch_to));
+    }
+}
+
+/// Parameters of a char search to repeat it
+#[derive(Debug, Clone, Copy)]
+struct CharSearch {
+    c: char,
+    direction: CursorDirection,
+    inclusive: bool,
+}
+
+/// Count of temporary files saves tried to create, which keeps their names
+/// unique within the process
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Create a new hidden temporary file next to `path` whose file name is
+/// `file_name`. Names taken by other saves or left by crashed ones are
+/// skipped.
+fn create_temp_file(path: &Path, file_name: &OsStr) -> io::Result<(PathBuf, File)> {
+    loop {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = path.with_file_name(temp_name);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
     }
 }
 
 impl LineLength for Rope {
     fn line_length(&self, line: usize) -> Option<usize> {
         // `line` arg is starting from 1
-
-        // FIXME: \n and \r do not cover all newline things afaik
-        // Also desired len is in grapheme clusters not String's .len()
         if line > 0 && line <= self.lines_count() {
-            let s = self.line(line - 1).to_string();
-            Some(s.trim_end_matches(|x| x == '\n' || x == '\r').len() + 1)
+            let line_start = self.line_to_char(line - 1);
+            let line_end = line_content_end(self, line - 1);
+            Some(graphemes_count(self, line_start, line_end) + 1)
         } else {
             None
         }
@@ -277,6 +1692,38 @@ impl LineLength for Rope {
     }
 }
 
+/// Lines of the text with columns also counted the way they are displayed
+#[derive(Debug, Clone, Copy)]
+struct DisplayLines<'a> {
+    rope: &'a Rope,
+    tab_width: usize,
+}
+
+impl<'a> DisplayLines<'a> {
+    fn new(rope: &'a Rope, tab_width: usize) -> Self {
+        DisplayLines { rope, tab_width }
+    }
+}
+
+impl LineLength for DisplayLines<'_> {
+    fn line_length(&self, line: usize) -> Option<usize> {
+        self.rope.line_length(line)
+    }
+
+    fn lines_count(&self) -> usize {
+        self.rope.lines_count()
+    }
+
+    fn display_col(&self, line: usize, col: usize) -> usize {
+        display_col(self.rope, line - 1, col, self.tab_width)
+    }
+
+    fn col_at_display_col(&self, line: usize, x: usize) -> Option<usize> {
+        self.line_length(line)
+            .map(|_| col_at_display_col(self.rope, line - 1, x, self.tab_width))
+    }
+}
+
 impl fmt::Display for Buffer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.rope)
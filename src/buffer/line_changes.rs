@@ -0,0 +1,107 @@
+//! Tracking of lines affected by edits to report them as deltas
+use crate::changes::ChangeSet;
+use crate::DeltaType;
+use ropey::Rope;
+use std::collections::BTreeSet;
+
+/// Lines inserted or removed by an edit
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LinesShift {
+    /// `count` lines were inserted starting from the index `idx`
+    Inserted { idx: usize, count: usize },
+    /// `count` lines were removed starting from the index `idx`
+    Removed { idx: usize, count: usize },
+}
+
+/// Accumulates how edits affected lines.
+///
+/// Shifts are kept in order of edits, so each one is described in
+/// line indices right after the previous one; changed lines are kept
+/// in line indices after all edits.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LineChanges {
+    shifts: Vec<LinesShift>,
+    changed: BTreeSet<usize>,
+}
+
+impl LineChanges {
+    pub(crate) fn new() -> Self {
+        Default::default()
+    }
+
+    /// Apply changes to the rope registering affected lines
+    pub(crate) fn apply(&mut self, rope: &mut Rope, changes: &ChangeSet) {
+        changes.apply_with(rope, |rope, line, lines_before| {
+            self.record(line, lines_before, rope.len_lines())
+        });
+    }
+
+    /// Register an edit which started on line `line` and changed lines
+    /// count from `lines_before` to `lines_after`. Lines are indexed from 1.
+    /// The starting line and inserted lines are marked as changed.
+    pub(crate) fn record(&mut self, line: usize, lines_before: usize, lines_after: usize) {
+        if lines_after > lines_before {
+            let count = lines_after - lines_before;
+            self.shift(LinesShift::Inserted {
+                idx: line + 1,
+                count,
+            });
+            // Inserted lines have contents to report too
+            self.changed.extend(line + 1..=line + count);
+        } else if lines_after < lines_before {
+            self.shift(LinesShift::Removed {
+                idx: line + 1,
+                count: lines_before - lines_after,
+            });
+        }
+        self.changed.insert(line);
+    }
+
+    /// Append changes which happened after these ones
+    pub(crate) fn merge(&mut self, other: LineChanges) {
+        for shift in other.shifts {
+            self.shift(shift);
+        }
+        self.changed.extend(other.changed);
+    }
+
+    fn shift(&mut self, shift: LinesShift) {
+        self.changed = match shift {
+            LinesShift::Inserted { idx, count } => self
+                .changed
+                .iter()
+                .map(|&l| if l >= idx { l + count } else { l })
+                .collect(),
+            LinesShift::Removed { idx, count } => self
+                .changed
+                .iter()
+                .filter(|&&l| l < idx || l >= idx + count)
+                .map(|&l| if l >= idx + count { l - count } else { l })
+                .collect(),
+        };
+        self.shifts.push(shift);
+    }
+
+    /// Build deltas: lines insertions and removals in order they happened
+    /// followed by contents of changed lines
+    pub(crate) fn into_deltas(self, rope: &Rope) -> Vec<DeltaType<'_>> {
+        let mut deltas: Vec<DeltaType> = self
+            .shifts
+            .into_iter()
+            .map(|shift| match shift {
+                LinesShift::Inserted { idx, count } => DeltaType::LinesInserted { idx, count },
+                LinesShift::Removed { idx, count } => DeltaType::LinesRemoved { idx, count },
+            })
+            .collect();
+        deltas.extend(
+            self.changed
+                .into_iter()
+                .filter(|&idx| idx <= rope.len_lines())
+                .map(|idx| DeltaType::LineChanged {
+                    idx,
+                    content: rope.line(idx - 1),
+                }),
+        );
+        deltas
+    }
+}
@@ -1,13 +1,38 @@
+mod auto_indent;
+mod brackets;
+mod change_set;
+mod display_cols;
+mod edit_deltas;
+mod find_char;
+mod grapheme_clusters;
+mod indent;
+mod insert_each;
+mod line_endings;
+mod line_motions;
+mod main_selection;
 mod multi_selection_delete;
 mod multi_selection_insert;
 mod multi_selection_movement;
+mod registers;
+mod replace;
+mod save;
+mod search;
 mod single_selection_delete;
 mod single_selection_deltas;
 mod single_selection_insert;
 mod single_selection_movement;
+mod soft_wrap;
+mod split;
+mod text_objects;
+mod transaction;
+mod undo_redo;
+mod undo_tree;
+mod viewport;
+mod word_motions;
 
 use super::Buffer;
 use crate::selections::storage::SelectionStorage;
+use crate::selections::{CursorDirection, SelectionUnbound};
 use std::fs::File;
 
 const TEXT: &'static str = "test_data/sample_text.txt";
@@ -23,3 +48,23 @@ fn load_buffer_with_selections(selections: &[(usize, usize, usize, usize, bool)]
     buffer.selection_storage = storage;
     buffer
 }
+
+fn buffer_with_selections(text: &str, selections: &[(usize, usize, usize, usize, bool)]) -> Buffer {
+    let mut buffer = Buffer::from_reader(text.as_bytes()).unwrap();
+    buffer.selection_storage = SelectionStorage::gen_from_tuples(selections);
+    buffer
+}
+
+fn selections(buffer: &Buffer) -> Vec<SelectionUnbound> {
+    buffer.internal_selections_iter().collect()
+}
+
+fn point(line: usize, col: usize) -> SelectionUnbound {
+    SelectionUnbound::new_quick(line, col, line, col, CursorDirection::Forward)
+}
+
+/// Get the line and column the main selection starts at
+fn main_from(buffer: &Buffer) -> (usize, usize) {
+    let from = buffer.main_selection().from();
+    (from.line(), from.col())
+}
@@ -0,0 +1,37 @@
+use super::*;
+use crate::{AutoIndent, IndentUnit};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "fn f() {\n    let a = [\n\tb";
+
+#[test]
+fn test_no_auto_indent_by_default() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 8, 2, 8, true)]);
+    buffer.insert("\n");
+    assert_eq!(buffer.to_string(), "fn f() {\n    let\n a = [\n\tb");
+    assert_eq!(selections(&buffer), vec![point(3, 1)]);
+}
+
+#[test]
+fn test_auto_indent_keep() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 8, 2, 8, true), (3, 3, 3, 3, true)]);
+    buffer.set_auto_indent(AutoIndent::Keep);
+    buffer.insert("\n");
+    assert_eq!(buffer.to_string(), "fn f() {\n    let\n     a = [\n\tb\n\t");
+    assert_eq!(selections(&buffer), vec![point(3, 5), point(5, 2)]);
+}
+
+#[test]
+fn test_auto_indent_after_brackets() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 9, 1, 9, true), (2, 14, 2, 14, true)]);
+    buffer.set_auto_indent(AutoIndent::AfterBrackets);
+    buffer.set_indent_unit(IndentUnit::Spaces(2));
+    buffer.insert("\n");
+    assert_eq!(
+        buffer.to_string(),
+        "fn f() {\n  \n    let a = [\n      \n\tb"
+    );
+    assert_eq!(selections(&buffer), vec![point(2, 3), point(4, 7)]);
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
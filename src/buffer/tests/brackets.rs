@@ -0,0 +1,56 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "fn main() {\n    let v = vec![(1, 2)];\n}";
+
+#[test]
+fn test_matching_bracket() {
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    let matching = |line, col| {
+        buffer
+            .matching_bracket(buffer.create_position(line, col))
+            .map(|p| (p.line(), p.col()))
+    };
+    assert_eq!(matching(1, 11), Some((3, 1)));
+    assert_eq!(matching(3, 1), Some((1, 11)));
+    assert_eq!(matching(2, 17), Some((2, 24)));
+    assert_eq!(matching(2, 23), Some((2, 18)));
+    assert_eq!(matching(1, 1), None);
+}
+
+#[test]
+fn test_goto_matching_bracket() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 8, 1, 8, true), (2, 5, 2, 5, true)]);
+    buffer.goto_matching_bracket(false);
+    assert_eq!(selections(&buffer), vec![point(1, 9), point(2, 5)]);
+    buffer.goto_matching_bracket(false);
+    assert_eq!(selections(&buffer), vec![point(1, 8), point(2, 5)]);
+}
+
+#[test]
+fn test_goto_matching_bracket_extend() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(3, 1, 3, 1, true)]);
+    buffer.goto_matching_bracket(true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            11,
+            3,
+            1,
+            CursorDirection::Backward
+        )]
+    );
+}
+
+#[test]
+fn test_custom_bracket_pairs() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 18, 2, 18, true)]);
+    buffer.set_bracket_pairs(&[('{', '}')]);
+    buffer.goto_matching_bracket(false);
+    assert_eq!(selections(&buffer), vec![point(2, 18)]);
+    buffer.set_bracket_pairs(&[('(', ')')]);
+    buffer.goto_matching_bracket(false);
+    assert_eq!(selections(&buffer), vec![point(2, 23)]);
+}
@@ -0,0 +1,61 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::LineEnding;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_last_change_replays_edits() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 3, true), (3, 2, 4, 5, true)]);
+    let mut replica = load_buffer().rope;
+
+    buffer.insert("kek\n");
+    buffer.last_change().apply(&mut replica);
+    assert_eq!(replica, buffer.rope);
+
+    buffer.delete();
+    buffer.last_change().apply(&mut replica);
+    assert_eq!(replica, buffer.rope);
+
+    buffer.convert_line_endings(LineEnding::CrLf);
+    buffer.last_change().apply(&mut replica);
+    assert_eq!(replica, buffer.rope);
+
+    buffer.earlier(2);
+    buffer.last_change().apply(&mut replica);
+    assert_eq!(replica, buffer.rope);
+}
+
+#[test]
+fn test_last_change_of_transaction() {
+    let mut buffer = load_buffer();
+    let mut replica = load_buffer().rope;
+    let result: Result<_, ()> = buffer.transaction(|b| {
+        b.insert("top");
+        b.move_down(1, false);
+        b.insert("kek");
+        Ok(())
+    });
+    assert!(result.is_ok());
+    buffer.last_change().apply(&mut replica);
+    assert_eq!(replica, buffer.rope);
+
+    let result = buffer.transaction(|b| {
+        b.insert("lol");
+        Err(())
+    });
+    assert!(result.is_err());
+    assert!(buffer.last_change().is_empty());
+}
+
+#[test]
+fn test_insert_moves_selections_on_the_same_line() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 1, true), (1, 3, 1, 5, true)]);
+    buffer.insert("ab");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 3, 1, 3, CursorDirection::Forward),
+            SelectionUnbound::new_quick(1, 5, 1, 9, CursorDirection::Forward),
+        ]
+    );
+}
@@ -0,0 +1,63 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "\tfoo\n世界ab\nabcdefgh\n\tx";
+
+#[test]
+fn test_display_col() {
+    let buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true)]);
+    assert_eq!(buffer.display_col(buffer.create_position(1, 1), 4), 0);
+    assert_eq!(buffer.display_col(buffer.create_position(1, 2), 4), 4);
+    assert_eq!(buffer.display_col(buffer.create_position(1, 2), 8), 8);
+    assert_eq!(buffer.display_col(buffer.create_position(2, 3), 4), 4);
+    assert_eq!(buffer.display_col(buffer.create_position(2, 5), 4), 6);
+}
+
+#[test]
+fn test_position_at_display_col() {
+    let buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true)]);
+    let col = |line, x| {
+        buffer
+            .position_at_display_col(line, x, 4)
+            .map(|position| position.col())
+    };
+    assert_eq!(col(1, 2), Some(1));
+    assert_eq!(col(1, 4), Some(2));
+    assert_eq!(col(2, 3), Some(2));
+    assert_eq!(col(2, 4), Some(3));
+    assert_eq!(col(2, 100), Some(5));
+    assert_eq!(col(5, 0), None);
+}
+
+#[test]
+fn test_move_up_keeps_display_col() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(3, 5, 3, 5, true)]);
+    buffer.move_up(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 3)]);
+    buffer.move_up(1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 2)]);
+}
+
+#[test]
+fn test_sticky_display_col_inside_wide_chars() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(3, 4, 3, 4, true)]);
+    buffer.move_up(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 2).with_sticky(4)]);
+    buffer.move_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(3, 4)]);
+
+    buffer.move_left(1, false);
+    buffer.move_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(4, 1).with_sticky(3)]);
+    buffer.move_up(1, false);
+    assert_eq!(selections(&buffer), vec![point(3, 3)]);
+}
+
+#[test]
+fn test_tab_width() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(3, 9, 3, 9, true)]);
+    assert_eq!(buffer.tab_width(), 4);
+    buffer.set_tab_width(8);
+    buffer.move_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(4, 2)]);
+}
@@ -0,0 +1,100 @@
+use super::*;
+use crate::selections::{CursorDirection, PositionUnbound, SelectionUnbound};
+use crate::{Delta, DeltaType};
+use pretty_assertions::assert_eq;
+
+fn delta_types<'a>(deltas: Vec<Delta<'_, 'a>>) -> Vec<DeltaType<'a>> {
+    deltas.into_iter().map(|d| d.delta_type).collect()
+}
+
+fn position(line: usize, col: usize) -> PositionUnbound {
+    PositionUnbound {
+        line: line.into(),
+        col: col.into(),
+    }
+}
+
+#[test]
+fn test_insert_deltas() {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 3, "kek");
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 3, true)]);
+    assert_eq!(
+        delta_types(buffer.insert("kek")),
+        vec![
+            DeltaType::LineChanged {
+                idx: 1,
+                content: reference_buffer.rope.line(0)
+            },
+            DeltaType::SelectionChanged {
+                identity: position(1, 3),
+                new_state: SelectionUnbound::new_quick(1, 6, 1, 6, CursorDirection::Forward)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_insert_newline_deltas() {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 3, "k\ne");
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 3, true)]);
+    assert_eq!(
+        delta_types(buffer.insert("k\ne")),
+        vec![
+            DeltaType::LinesInserted { idx: 2, count: 1 },
+            DeltaType::LineChanged {
+                idx: 1,
+                content: reference_buffer.rope.line(0)
+            },
+            DeltaType::LineChanged {
+                idx: 2,
+                content: reference_buffer.rope.line(1)
+            },
+            DeltaType::SelectionChanged {
+                identity: position(1, 3),
+                new_state: SelectionUnbound::new_quick(2, 2, 2, 2, CursorDirection::Forward)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_delete_lines_deltas() {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.delete_for_test(1, 5, 3, 1);
+    let mut buffer = load_buffer_with_selections(&vec![(1, 5, 3, 1, true)]);
+    assert_eq!(
+        delta_types(buffer.delete()),
+        vec![
+            DeltaType::LinesRemoved { idx: 2, count: 2 },
+            DeltaType::LineChanged {
+                idx: 1,
+                content: reference_buffer.rope.line(0)
+            },
+            DeltaType::SelectionChanged {
+                identity: position(1, 5),
+                new_state: SelectionUnbound::new_quick(1, 5, 1, 5, CursorDirection::Forward)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_delete_merged_selections_deltas() {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.delete_for_test(1, 1, 1, 2);
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 1, true), (1, 2, 1, 2, true)]);
+    assert_eq!(
+        delta_types(buffer.delete()),
+        vec![
+            DeltaType::LineChanged {
+                idx: 1,
+                content: reference_buffer.rope.line(0)
+            },
+            DeltaType::SelectionDeleted {
+                identity: position(1, 2)
+            },
+        ]
+    );
+}
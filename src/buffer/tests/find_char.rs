@@ -0,0 +1,69 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "let (a, b) = (1, 2);\nfoo(a, b);";
+
+#[test]
+fn test_find_char_forward() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true), (2, 1, 2, 1, true)]);
+    buffer.find_char(',', CursorDirection::Forward, true, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 7), point(2, 6)]);
+    buffer.find_char(',', CursorDirection::Forward, true, 2, false);
+    assert_eq!(selections(&buffer), vec![point(1, 7), point(2, 6)]);
+    buffer.find_char(')', CursorDirection::Forward, false, 1, true);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 7, 1, 9, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 6, 2, 8, CursorDirection::Forward),
+        ]
+    );
+}
+
+#[test]
+fn test_find_char_backward() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 20, 1, 20, true)]);
+    buffer.find_char('(', CursorDirection::Backward, true, 2, false);
+    assert_eq!(selections(&buffer), vec![point(1, 5)]);
+    buffer.find_char('l', CursorDirection::Backward, false, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 2)]);
+}
+
+#[test]
+fn test_repeat_find() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true)]);
+    assert!(buffer.repeat_find(1, false, false).is_empty());
+    buffer.find_char(',', CursorDirection::Forward, false, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 6)]);
+    buffer.repeat_find(1, false, false);
+    assert_eq!(selections(&buffer), vec![point(1, 15)]);
+    buffer.repeat_find(1, true, false);
+    assert_eq!(selections(&buffer), vec![point(1, 8)]);
+}
+
+#[test]
+fn test_find_char_across_lines() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 16, 1, 16, true)]);
+    buffer.find_char('b', CursorDirection::Forward, true, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 16)]);
+    buffer.set_find_across_lines(true);
+    buffer.find_char('b', CursorDirection::Forward, true, 1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 8)]);
+    buffer.repeat_find(2, true, false);
+    assert_eq!(selections(&buffer), vec![point(2, 8)]);
+    buffer.repeat_find(1, true, false);
+    assert_eq!(selections(&buffer), vec![point(1, 9)]);
+}
+
+#[test]
+fn test_find_char_next_to_cursor() {
+    let mut buffer = Buffer::from_reader("abxcx".as_bytes()).unwrap();
+    buffer.move_right(1, false);
+    buffer.find_char('x', CursorDirection::Forward, false, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 2)]);
+    buffer.repeat_find(1, false, false);
+    assert_eq!(selections(&buffer), vec![point(1, 4)]);
+    buffer.find_char('x', CursorDirection::Backward, false, 1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 4)]);
+}
@@ -0,0 +1,50 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "Zwölf Boxkämpfer 👩‍👩‍👧‍👦 jagen\nVikto\u{301}r 🇷🇺\r\nquer";
+
+#[test]
+fn test_line_length() {
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    assert_eq!(buffer.line_length(1), Some(25));
+    assert_eq!(buffer.line_length(2), Some(9));
+    assert_eq!(buffer.line_length(3), Some(5));
+}
+
+#[test]
+fn test_move_over_clusters() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_right(17, false);
+    buffer.move_right(1, true);
+    let selection = buffer.selections_iter().next().unwrap();
+    assert_eq!(selection.from().char_idx(), 17);
+    assert_eq!(selection.to().char_idx(), 24);
+
+    buffer.move_down(1, false);
+    buffer.move_left(3, false);
+    assert_eq!(selections(&buffer), vec![point(2, 6)]);
+}
+
+#[test]
+fn test_insert_clusters() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_down(1, false);
+    buffer.move_right(7, false);
+    buffer.insert("🇺🇦e\u{301}");
+    assert_eq!(
+        buffer.to_string(),
+        "Zwölf Boxkämpfer 👩‍👩‍👧‍👦 jagen\nVikto\u{301}r 🇺🇦e\u{301}🇷🇺\r\nquer"
+    );
+    assert_eq!(selections(&buffer), vec![point(2, 10)]);
+}
+
+#[test]
+fn test_delete_clusters() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_right(17, false);
+    buffer.delete();
+    buffer.move_down(1, false);
+    buffer.move_left(14, true);
+    buffer.delete();
+    assert_eq!(buffer.to_string(), "Zwölf Boxkämpfer  quer");
+}
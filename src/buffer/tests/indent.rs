@@ -0,0 +1,60 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::IndentUnit;
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "fn f() {\nlet a;\n\n\t  let b;\n}";
+
+#[test]
+fn test_indent_lines_once() {
+    let mut buffer = buffer_with_selections(
+        TEXT,
+        &vec![(2, 1, 2, 3, true), (2, 5, 4, 2, false), (4, 4, 4, 4, true)],
+    );
+    buffer.indent(1);
+    assert_eq!(
+        buffer.to_string(),
+        "fn f() {\n    let a;\n\n    \t  let b;\n}"
+    );
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(2, 5, 2, 7, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 9, 4, 6, CursorDirection::Backward),
+            SelectionUnbound::new_quick(4, 8, 4, 8, CursorDirection::Forward),
+        ]
+    );
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
+
+#[test]
+fn test_indent_with_tabs() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 2, 1, true)]);
+    buffer.set_indent_unit(IndentUnit::Tab);
+    buffer.indent(2);
+    assert_eq!(
+        buffer.to_string(),
+        "\t\tfn f() {\n\t\tlet a;\n\n\t  let b;\n}"
+    );
+}
+
+#[test]
+fn test_dedent() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(4, 5, 5, 1, true)]);
+    buffer.set_indent_unit(IndentUnit::Spaces(2));
+    buffer.dedent(1);
+    assert_eq!(buffer.to_string(), "fn f() {\nlet a;\n\n  let b;\n}");
+    buffer.dedent(3);
+    assert_eq!(buffer.to_string(), "fn f() {\nlet a;\n\nlet b;\n}");
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            4,
+            2,
+            5,
+            1,
+            CursorDirection::Forward
+        )]
+    );
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "ab cd\nef";
+
+fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_insert_each_on_same_line() {
+    let mut buffer = buffer_with_selections(
+        TEXT,
+        &vec![(1, 1, 1, 1, true), (1, 4, 1, 4, true), (2, 2, 2, 2, true)],
+    );
+    buffer.insert_each(texts(&["1\n22", "333", ""]));
+    assert_eq!(buffer.to_string(), "1\n22ab 333cd\nef");
+    assert_eq!(
+        selections(&buffer),
+        vec![point(2, 3), point(2, 9), point(3, 2)]
+    );
+}
+
+#[test]
+fn test_insert_each_fewer_texts() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 2, 1, 2, true), (2, 1, 2, 1, true)]);
+    buffer.insert_each(texts(&["x\ny"]));
+    assert_eq!(buffer.to_string(), "ax\nyb cd\nef");
+    assert_eq!(selections(&buffer), vec![point(2, 2), point(3, 1)]);
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
+
+#[test]
+fn test_replace_each() {
+    let mut buffer = buffer_with_selections(
+        TEXT,
+        &vec![(1, 1, 1, 2, true), (1, 4, 1, 5, false), (2, 1, 2, 2, true)],
+    );
+    buffer.replace_each(texts(&["one\ntwo", "3"]));
+    assert_eq!(buffer.to_string(), "one\ntwo 3\nef");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 1, 2, 3, CursorDirection::Forward),
+            point(2, 5),
+            SelectionUnbound::new_quick(3, 1, 3, 2, CursorDirection::Forward),
+        ]
+    );
+}
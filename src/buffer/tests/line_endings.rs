@@ -0,0 +1,106 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::LineEnding;
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "one\r\ntwo\r\nthree\u{2028}four\nfive\u{0085}six\u{000C}seven";
+
+#[test]
+fn test_line_lengths() {
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    assert_eq!(buffer.lines_count(), 7);
+    let lengths: Vec<usize> = (1..=7).map(|l| buffer.line_length(l).unwrap()).collect();
+    assert_eq!(lengths, vec![4, 4, 6, 5, 5, 4, 6]);
+}
+
+#[test]
+fn test_detect_line_ending() {
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    assert_eq!(buffer.line_ending(), LineEnding::CrLf);
+    let buffer = load_buffer();
+    assert_eq!(buffer.line_ending(), LineEnding::Lf);
+    assert_eq!(Buffer::empty().line_ending(), LineEnding::Lf);
+}
+
+#[test]
+fn test_delete_line_end() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_right(3, false);
+    buffer.delete();
+    buffer.move_down(1, false);
+    buffer.move_right(2, false);
+    buffer.delete();
+    assert_eq!(
+        buffer.to_string(),
+        "onetwo\r\nthreefour\nfive\u{0085}six\u{000C}seven"
+    );
+}
+
+#[test]
+fn test_insert_crlf() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_right(1, false);
+    buffer.insert("x\r\ny\u{2028}z");
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            3,
+            2,
+            3,
+            2,
+            CursorDirection::Forward
+        )]
+    );
+}
+
+#[test]
+fn test_convert_line_endings() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.move_down(3, false);
+    buffer.move_right(2, true);
+    let selections_before = selections(&buffer);
+
+    buffer.convert_line_endings(LineEnding::Lf);
+    assert_eq!(buffer.line_ending(), LineEnding::Lf);
+    assert_eq!(
+        buffer.to_string(),
+        "one\ntwo\nthree\nfour\nfive\nsix\u{000C}seven"
+    );
+    assert_eq!(selections(&buffer), selections_before);
+
+    buffer.convert_line_endings(LineEnding::CrLf);
+    assert_eq!(
+        buffer.to_string(),
+        "one\r\ntwo\r\nthree\r\nfour\r\nfive\r\nsix\u{000C}seven"
+    );
+    assert_eq!(selections(&buffer), selections_before);
+
+    buffer.undo();
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
+
+#[test]
+fn test_line_ending_follows_history() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.convert_line_endings(LineEnding::Lf);
+    buffer.undo();
+    assert_eq!(buffer.line_ending(), LineEnding::CrLf);
+    buffer.redo();
+    assert_eq!(buffer.line_ending(), LineEnding::Lf);
+
+    let result: Result<_, ()> = buffer.transaction(|buffer| {
+        buffer.convert_line_endings(LineEnding::Cr);
+        Err(())
+    });
+    assert!(result.is_err());
+    assert_eq!(buffer.line_ending(), LineEnding::Lf);
+
+    // The text has no line breaks to convert, but the line ending changes
+    let mut buffer = Buffer::from_reader("one".as_bytes()).unwrap();
+    buffer.convert_line_endings(LineEnding::CrLf);
+    buffer.undo();
+    assert_eq!(buffer.line_ending(), LineEnding::Lf);
+    buffer.redo();
+    assert_eq!(buffer.line_ending(), LineEnding::CrLf);
+}
@@ -0,0 +1,108 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::{Delta, DeltaType};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "  indented line\nshort\n\n    last one";
+
+fn delta_types<'a>(deltas: Vec<Delta<'_, 'a>>) -> Vec<DeltaType<'a>> {
+    deltas.into_iter().map(|d| d.delta_type).collect()
+}
+
+#[test]
+fn test_move_to_line_start_and_end() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 5, true), (2, 2, 2, 2, true)]);
+    buffer.move_to_line_end(false);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            point(1, 16).with_sticky(usize::MAX),
+            point(2, 6).with_sticky(usize::MAX),
+        ]
+    );
+    buffer.move_to_line_start(true);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 1, 1, 16, CursorDirection::Backward),
+            SelectionUnbound::new_quick(2, 1, 2, 6, CursorDirection::Backward),
+        ]
+    );
+}
+
+#[test]
+fn test_line_end_is_sticky() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 1, 2, 1, true)]);
+    buffer.move_to_line_end(false);
+    buffer.move_down(1, false);
+    assert_eq!(
+        selections(&buffer),
+        vec![point(3, 1).with_sticky(usize::MAX)]
+    );
+    buffer.move_down(1, false);
+    assert_eq!(
+        selections(&buffer),
+        vec![point(4, 13).with_sticky(usize::MAX)]
+    );
+}
+
+#[test]
+fn test_smart_home() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 8, 1, 8, true), (3, 1, 3, 1, true)]);
+    buffer.move_smart_home(false);
+    assert_eq!(selections(&buffer), vec![point(1, 3), point(3, 1)]);
+    buffer.move_smart_home(false);
+    assert_eq!(selections(&buffer), vec![point(1, 1), point(3, 1)]);
+    buffer.move_to_first_non_blank(false);
+    assert_eq!(selections(&buffer), vec![point(1, 3), point(3, 1)]);
+}
+
+#[test]
+fn test_move_to_buffer_boundaries() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 5, true), (2, 2, 2, 2, true)]);
+    buffer.move_to_buffer_end(false);
+    assert_eq!(selections(&buffer), vec![point(4, 13)]);
+    buffer.move_to_buffer_start(true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            1,
+            4,
+            13,
+            CursorDirection::Backward
+        )]
+    );
+}
+
+#[test]
+fn test_goto_line() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 10, 1, 10, true)]);
+    buffer.goto_line(2, false);
+    assert_eq!(selections(&buffer), vec![point(2, 6).with_sticky(10)]);
+    buffer.goto_line(100, false);
+    assert_eq!(selections(&buffer), vec![point(4, 10)]);
+    buffer.goto_line(0, true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            10,
+            4,
+            10,
+            CursorDirection::Backward
+        )]
+    );
+}
+
+#[test]
+fn test_line_motions_deltas() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 2, 2, 2, true)]);
+    let deltas = buffer.move_to_line_end(false);
+    assert_eq!(
+        delta_types(deltas),
+        vec![DeltaType::SelectionAdded {
+            selection: point(2, 6).with_sticky(usize::MAX),
+        }]
+    );
+}
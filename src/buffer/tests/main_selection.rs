@@ -0,0 +1,125 @@
+use super::*;
+use crate::selections::{PositionUnbound, SelectionUnbound};
+use crate::{DeltaType, Error};
+use pretty_assertions::assert_eq;
+
+fn position(line: usize, col: usize) -> PositionUnbound {
+    PositionUnbound {
+        line: line.into(),
+        col: col.into(),
+    }
+}
+
+fn main_selection(buffer: &Buffer) -> SelectionUnbound {
+    buffer.main_selection().selection
+}
+
+fn buffer() -> Buffer {
+    Buffer::from_reader("abcd\nabcd\nabcd\nabcd".as_bytes()).unwrap()
+}
+
+fn three_cursors() -> Buffer {
+    let mut buffer = buffer();
+    buffer.move_right(2, false);
+    buffer.place_selection_under();
+    buffer.place_selection_under();
+    buffer
+}
+
+#[test]
+fn test_main_selection_placed_under() {
+    let mut buffer = buffer();
+    assert_eq!(main_selection(&buffer), point(1, 1));
+    buffer.move_right(2, false);
+    let deltas: Vec<DeltaType> = buffer
+        .place_selection_under()
+        .into_iter()
+        .map(|d| d.delta_type)
+        .collect();
+    assert_eq!(
+        deltas.last(),
+        Some(&DeltaType::MainSelectionChanged {
+            identity: position(2, 3)
+        })
+    );
+    assert_eq!(main_selection(&buffer), point(2, 3));
+    let main: Vec<bool> = buffer.selections_iter().map(|s| s.is_main()).collect();
+    assert_eq!(main, vec![false, true]);
+    let main: Vec<bool> = buffer.selections_at(2).map(|s| s.is_main()).collect();
+    assert_eq!(main, vec![true]);
+}
+
+#[test]
+fn test_rotate_main() {
+    let mut buffer = three_cursors();
+    assert_eq!(main_selection(&buffer), point(3, 3));
+    let deltas: Vec<DeltaType> = buffer
+        .rotate_main(1)
+        .into_iter()
+        .map(|d| d.delta_type)
+        .collect();
+    assert_eq!(
+        deltas,
+        vec![DeltaType::MainSelectionChanged {
+            identity: position(1, 3)
+        }]
+    );
+    buffer.rotate_main(-2);
+    assert_eq!(main_selection(&buffer), point(2, 3));
+    buffer.rotate_main(3);
+    assert_eq!(main_selection(&buffer), point(2, 3));
+}
+
+#[test]
+fn test_set_main() {
+    let mut buffer = three_cursors();
+    buffer.set_main(0).unwrap();
+    assert_eq!(main_selection(&buffer), point(1, 3));
+    assert!(buffer.set_main(0).unwrap().is_empty());
+    assert!(matches!(
+        buffer.set_main(3),
+        Err(Error::SelectionNotFound(3))
+    ));
+    assert_eq!(main_selection(&buffer), point(1, 3));
+}
+
+#[test]
+fn test_keep_only_main() {
+    let mut buffer = three_cursors();
+    buffer.set_main(1).unwrap();
+    buffer.keep_only_main();
+    assert_eq!(
+        buffer.internal_selections_iter().collect::<Vec<_>>(),
+        vec![point(2, 3)]
+    );
+    assert_eq!(main_selection(&buffer), point(2, 3));
+}
+
+#[test]
+fn test_remove_main() {
+    let mut buffer = three_cursors();
+    buffer.set_main(0).unwrap();
+    buffer.remove_main().unwrap();
+    assert_eq!(main_selection(&buffer), point(2, 3));
+    buffer.remove_main().unwrap();
+    assert_eq!(main_selection(&buffer), point(3, 3));
+    assert!(matches!(buffer.remove_main(), Err(Error::NothingSelected)));
+    assert_eq!(
+        buffer.internal_selections_iter().collect::<Vec<_>>(),
+        vec![point(3, 3)]
+    );
+}
+
+#[test]
+fn test_main_kept_on_moving_onto_next_selection() {
+    let mut buffer = Buffer::from_reader("abcdefghijklmnopq".as_bytes()).unwrap();
+    buffer.move_to_line_end(true);
+    buffer
+        .select_matches(&crate::Regex::new("a|d").unwrap())
+        .unwrap();
+    buffer.set_main(0).unwrap();
+    buffer.move_right(3, false);
+    assert_eq!(main_selection(&buffer), point(1, 4));
+    let main: Vec<bool> = buffer.selections_iter().map(|s| s.is_main()).collect();
+    assert_eq!(main, vec![true, false]);
+}
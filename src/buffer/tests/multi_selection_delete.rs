@@ -8,8 +8,10 @@ fn test_delete_single_line() {
         (1, 7, 1, 8, true),
         (1, 56, 1, 57, true),
     ]);
+    buffer.set_main(2).unwrap();
     buffer.delete();
     buffer.delete();
+    assert_eq!(main_from(&buffer), (1, 50));
     let mut reference_buffer = load_buffer_with_selections(&vec![
         (1, 3, 1, 3, true),
         (1, 4, 1, 4, true),
@@ -27,9 +29,11 @@ fn test_delete_single_line_merge() {
         (1, 7, 1, 8, true),
         (1, 56, 1, 57, true),
     ]);
+    buffer.set_main(1).unwrap();
     buffer.delete();
     buffer.delete();
     buffer.delete();
+    assert_eq!(main_from(&buffer), (1, 3));
     let mut reference_buffer =
         load_buffer_with_selections(&vec![(1, 3, 1, 3, true), (1, 48, 1, 48, true)]);
     reference_buffer.delete_for_test(1, 1, 1, 59);
@@ -44,7 +48,9 @@ fn test_deletion_multiple_lines() {
         (4, 10, 4, 20, true),
         (5, 10, 5, 20, true),
     ]);
+    buffer.set_main(1).unwrap();
     buffer.delete();
+    assert_eq!(main_from(&buffer), (4, 10));
     let mut reference_buffer = load_buffer_with_selections(&vec![
         (3, 10, 3, 10, true),
         (4, 10, 4, 10, true),
@@ -9,6 +9,7 @@ fn test_insert() {
         (4, 33, 4, 33, true),
     ]);
     buffer.insert(" (top kek) ");
+    assert_eq!(main_from(&buffer), (1, 14));
     let mut reference_buffer = load_buffer_with_selections(&vec![
         (1, 14, 1, 14, true),
         (3, 22, 3, 22, true),
@@ -20,6 +21,17 @@ fn test_insert() {
     assert_eq!(buffer, reference_buffer);
 }
 
+#[test]
+fn test_insert_keeps_main() {
+    let mut buffer = Buffer::from_reader("abcdefghijklmnopq".as_bytes()).unwrap();
+    buffer.selection_storage =
+        SelectionStorage::gen_from_tuples(&vec![(1, 1, 1, 1, true), (1, 4, 1, 4, true)]);
+    buffer.set_main(0).unwrap();
+    buffer.insert("xyz");
+    assert_eq!(main_from(&buffer), (1, 4));
+    assert_eq!(buffer.selection_storage.main_index(), 0);
+}
+
 #[test]
 fn test_insert_before_selection() {
     let mut buffer = load_buffer_with_selections(&vec![
@@ -27,7 +39,9 @@ fn test_insert_before_selection() {
         (3, 11, 3, 21, false),
         (4, 33, 4, 43, false),
     ]);
+    buffer.set_main(1).unwrap();
     buffer.insert(" (top kek) ");
+    assert_eq!(main_from(&buffer), (3, 22));
     let mut reference_buffer = load_buffer_with_selections(&vec![
         (1, 14, 1, 24, false),
         (3, 22, 3, 32, false),
@@ -0,0 +1,82 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::{Registers, DEFAULT_REGISTER};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "one two\nthree four";
+
+fn registers_with(name: char, values: &[&str]) -> Registers {
+    let mut registers = Registers::new();
+    registers.set(name, values.iter().map(|v| v.to_string()).collect());
+    registers
+}
+
+#[test]
+fn test_yank() {
+    let buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 3, true), (2, 7, 2, 10, false)]);
+    let mut registers = Registers::new();
+    buffer.yank(&mut registers, 'a');
+    assert_eq!(
+        registers.get('a'),
+        Some(&["one".to_owned(), "four".to_owned()][..])
+    );
+    assert_eq!(registers.get(DEFAULT_REGISTER), None);
+}
+
+#[test]
+fn test_paste_after_per_selection() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 3, true), (2, 1, 2, 5, false)]);
+    let registers = registers_with(DEFAULT_REGISTER, &["1", "22"]);
+    buffer.paste_after(&registers, DEFAULT_REGISTER);
+    assert_eq!(buffer.to_string(), "one1 two\nthree22 four");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 4, 1, 4, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 6, 2, 7, CursorDirection::Backward),
+        ]
+    );
+}
+
+#[test]
+fn test_paste_before_whole_content() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 7, true), (2, 1, 2, 5, true)]);
+    let registers = registers_with('x', &["a", "b", "c"]);
+    buffer.paste_before(&registers, 'x');
+    assert_eq!(buffer.to_string(), "one a\nb\nctwo\na\nb\ncthree four");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 5, 3, 1, CursorDirection::Forward),
+            SelectionUnbound::new_quick(4, 1, 6, 1, CursorDirection::Forward),
+        ]
+    );
+}
+
+#[test]
+fn test_paste_replace() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 3, true), (2, 7, 2, 10, true)]);
+    let mut registers = Registers::new();
+    buffer.yank(&mut registers, DEFAULT_REGISTER);
+    buffer.selection_storage =
+        SelectionStorage::gen_from_tuples(&vec![(1, 5, 1, 7, true), (2, 1, 2, 5, true)]);
+    buffer.paste_replace(&registers, DEFAULT_REGISTER);
+    assert_eq!(buffer.to_string(), "one one\nfour four");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 5, 1, 7, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 1, 2, 4, CursorDirection::Forward),
+        ]
+    );
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
+
+#[test]
+fn test_paste_empty_register() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 3, true)]);
+    let registers = Registers::new();
+    assert!(buffer.paste_after(&registers, 'a').is_empty());
+    assert_eq!(buffer.to_string(), TEXT);
+}
@@ -0,0 +1,55 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "foo bar\nbaz";
+
+#[test]
+fn test_replace_keeps_extent_and_direction() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 3, true), (1, 5, 2, 1, false)]);
+    buffer.replace("quux");
+    assert_eq!(buffer.to_string(), "quux quuxaz");
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 1, 1, 4, CursorDirection::Forward),
+            SelectionUnbound::new_quick(1, 6, 1, 9, CursorDirection::Backward),
+        ]
+    );
+    buffer.undo();
+    assert_eq!(buffer.to_string(), TEXT);
+}
+
+#[test]
+fn test_replace_with_lines() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 7, false)]);
+    buffer.replace("a\nb");
+    assert_eq!(buffer.to_string(), "foo a\nb\nbaz");
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            5,
+            2,
+            1,
+            CursorDirection::Backward
+        )]
+    );
+}
+
+#[test]
+fn test_replace_with_empty() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 4, false)]);
+    buffer.replace("");
+    assert_eq!(buffer.to_string(), "bar\nbaz");
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            1,
+            1,
+            1,
+            CursorDirection::Forward
+        )]
+    );
+}
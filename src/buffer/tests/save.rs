@@ -0,0 +1,128 @@
+use super::*;
+use crate::Error;
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::PathBuf;
+
+const TEXT: &str = "\u{FEFF}first\r\nsecond\nthird";
+
+/// Create an empty directory for the test to save files in
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("coredit-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_bom_is_not_a_text() {
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    assert!(buffer.has_bom());
+    assert_eq!(buffer.to_string(), "first\r\nsecond\nthird");
+    assert_eq!(buffer.line_length(1), Some(6));
+}
+
+#[test]
+fn test_write_to() {
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.insert("new ");
+    let mut bytes = vec![];
+    buffer.write_to(&mut bytes).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "\u{FEFF}new first\r\nsecond\nthird"
+    );
+
+    let buffer = load_buffer();
+    let mut bytes = vec![];
+    buffer.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, fs::read(super::TEXT).unwrap());
+}
+
+#[test]
+fn test_save_to_path() {
+    let dir = temp_dir("save");
+    let path = dir.join("text.txt");
+    let mut buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.save_to_path(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), TEXT);
+
+    buffer.insert("new ");
+    buffer.save_to_path(&path).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "\u{FEFF}new first\r\nsecond\nthird"
+    );
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_skips_taken_temp_names() {
+    let dir = temp_dir("taken");
+    let path = dir.join("text.txt");
+    // Temporary files left by a crashed run which had the same process id
+    for n in 0..64 {
+        let name = format!(".text.txt.{}.{}.tmp", std::process::id(), n);
+        fs::write(dir.join(name), "stale").unwrap();
+    }
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    buffer.save_to_path(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), TEXT);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 65);
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+                buffer.save_to_path(path)
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap().unwrap();
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), TEXT);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 65);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_keeps_permissions_and_symlinks() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let dir = temp_dir("permissions");
+    let path = dir.join("script.sh");
+    let link = dir.join("link.sh");
+    fs::write(&path, "old").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+    symlink(&path, &link).unwrap();
+
+    let buffer = Buffer::from_reader("new".as_bytes()).unwrap();
+    buffer.save_to_path(&link).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_error() {
+    let dir = temp_dir("error");
+    let path = dir.join("missing").join("text.txt");
+    let buffer = Buffer::from_reader(TEXT.as_bytes()).unwrap();
+    match buffer.save_to_path(&path) {
+        Err(Error::Save {
+            path: error_path, ..
+        }) => assert_eq!(error_path, path),
+        result => panic!("Unexpected result {:?}", result),
+    }
+    assert!(!path.exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,111 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::{Error, Regex};
+use pretty_assertions::assert_eq;
+
+fn forward(from_line: usize, from_col: usize, to_line: usize, to_col: usize) -> SelectionUnbound {
+    SelectionUnbound::new_quick(
+        from_line,
+        from_col,
+        to_line,
+        to_col,
+        CursorDirection::Forward,
+    )
+}
+
+#[test]
+fn test_select_matches() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 30, true), (3, 1, 4, 9, false)]);
+    buffer
+        .select_matches(&Regex::new(r"\bon\w*").unwrap())
+        .unwrap();
+    assert_eq!(
+        selections(&buffer),
+        vec![forward(3, 11, 3, 13), forward(4, 6, 4, 8),]
+    );
+    assert_eq!(
+        buffer.selection_storage.main_selection(),
+        Some(&forward(4, 6, 4, 8))
+    );
+}
+
+#[test]
+fn test_select_matches_drops_selections_without_matches() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 4, true), (3, 1, 3, 4, true)]);
+    buffer.select_matches(&Regex::new("is").unwrap()).unwrap();
+    assert_eq!(selections(&buffer), vec![forward(1, 3, 1, 4)]);
+}
+
+#[test]
+fn test_select_matches_across_lines() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 50, 3, 4, true)]);
+    buffer
+        .select_matches(&Regex::new(r"lines\n+Like").unwrap())
+        .unwrap();
+    assert_eq!(selections(&buffer), vec![forward(1, 55, 3, 4)]);
+}
+
+#[test]
+fn test_select_matches_nothing_found() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 4, true)]);
+    let selections_before = selections(&buffer);
+    assert!(matches!(
+        buffer.select_matches(&Regex::new("kek").unwrap()),
+        Err(Error::NothingSelected)
+    ));
+    assert_eq!(selections(&buffer), selections_before);
+}
+
+#[test]
+fn test_select_matches_in_grapheme_clusters() {
+    let mut buffer = Buffer::from_reader("e\u{301}te\u{301} e\u{301}".as_bytes()).unwrap();
+    buffer.move_right(3, true);
+    buffer.select_matches(&Regex::new("e").unwrap()).unwrap();
+    assert_eq!(
+        selections(&buffer),
+        vec![forward(1, 1, 1, 1), forward(1, 3, 1, 3)]
+    );
+}
+
+#[test]
+fn test_keep_selections_matching() {
+    let mut buffer = load_buffer_with_selections(&vec![
+        (1, 1, 1, 4, true),
+        (3, 1, 3, 4, true),
+        (4, 1, 4, 4, true),
+    ]);
+    buffer
+        .keep_selections_matching(&Regex::new("^Th").unwrap(), false)
+        .unwrap();
+    assert_eq!(
+        selections(&buffer),
+        vec![forward(1, 1, 1, 4), forward(4, 1, 4, 4)]
+    );
+    assert_eq!(
+        buffer.selection_storage.main_selection(),
+        Some(&forward(1, 1, 1, 4))
+    );
+}
+
+#[test]
+fn test_keep_selections_not_matching() {
+    let mut buffer = load_buffer_with_selections(&vec![
+        (1, 1, 1, 4, true),
+        (3, 1, 3, 4, true),
+        (4, 1, 4, 4, true),
+    ]);
+    buffer
+        .keep_selections_matching(&Regex::new("^Th").unwrap(), true)
+        .unwrap();
+    assert_eq!(selections(&buffer), vec![forward(3, 1, 3, 4)]);
+    assert_eq!(
+        buffer.selection_storage.main_selection(),
+        Some(&forward(3, 1, 3, 4))
+    );
+
+    assert!(matches!(
+        buffer.keep_selections_matching(&Regex::new("Like").unwrap(), true),
+        Err(Error::NothingSelected)
+    ));
+    assert_eq!(selections(&buffer), vec![forward(3, 1, 3, 4)]);
+}
@@ -0,0 +1,126 @@
+use super::*;
+use crate::layout::{VisualRow, WrapMode};
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "aaaa bbbb cccc\nab\ndddd eeee";
+
+fn wrapped_buffer_with_selections(selections: &[(usize, usize, usize, usize, bool)]) -> Buffer {
+    let mut buffer = buffer_with_selections(TEXT, selections);
+    buffer.set_soft_wrap(5, WrapMode::Word);
+    buffer
+}
+
+fn all_rows(buffer: &Buffer) -> Vec<VisualRow> {
+    (1..=buffer.lines_count())
+        .flat_map(|line| buffer.visual_rows(line).unwrap())
+        .collect()
+}
+
+/// Check rows updated on edits are the same as if the whole text is wrapped
+fn assert_rewrapped(buffer: &Buffer) {
+    let mut wrapped = Buffer::from_reader(buffer.to_string().as_bytes()).unwrap();
+    wrapped.set_soft_wrap(5, WrapMode::Word);
+    assert_eq!(all_rows(buffer), all_rows(&wrapped));
+}
+
+#[test]
+fn test_visual_rows() {
+    let buffer = wrapped_buffer_with_selections(&vec![(1, 1, 1, 1, true)]);
+    let starts: Vec<usize> = buffer
+        .visual_rows(1)
+        .unwrap()
+        .iter()
+        .map(|row| row.start)
+        .collect();
+    assert_eq!(starts, vec![1, 6, 11]);
+    assert_eq!(
+        buffer.visual_rows(2),
+        Some(vec![VisualRow {
+            line: 2,
+            start: 1,
+            end: 4
+        }])
+    );
+    assert_eq!(buffer.visual_rows(4), None);
+}
+
+#[test]
+fn test_rewrap_on_edits() {
+    let mut buffer = wrapped_buffer_with_selections(&vec![(1, 3, 1, 3, true), (2, 2, 2, 2, true)]);
+    buffer.insert("xx yy\nzzzzzzz ");
+    assert_rewrapped(&buffer);
+    buffer.move_left(3, true);
+    buffer.delete();
+    assert_rewrapped(&buffer);
+    buffer.undo();
+    assert_rewrapped(&buffer);
+    buffer.undo();
+    assert_rewrapped(&buffer);
+    buffer.redo();
+    assert_rewrapped(&buffer);
+    let _ = buffer.transaction(|buffer| -> Result<(), ()> {
+        buffer.insert("q\n\nqqqq qq");
+        Err(())
+    });
+    assert_rewrapped(&buffer);
+}
+
+#[test]
+fn test_tab_width_rewraps() {
+    let mut buffer = wrapped_buffer_with_selections(&vec![(2, 1, 2, 1, true)]);
+    buffer.insert("\t");
+    assert_eq!(buffer.visual_rows(2).unwrap().len(), 2);
+    buffer.set_tab_width(2);
+    assert_eq!(buffer.visual_rows(2).unwrap().len(), 1);
+}
+
+#[test]
+fn test_move_visual_down_and_up() {
+    let mut buffer = wrapped_buffer_with_selections(&vec![(1, 2, 1, 2, true)]);
+    let expected = vec![(1, 7), (1, 12), (2, 2), (3, 2), (3, 7), (3, 7)];
+    for (line, col) in expected {
+        buffer.move_visual_down(1, false);
+        assert_eq!(selections(&buffer), vec![point(line, col)]);
+    }
+    buffer.move_visual_up(3, false);
+    assert_eq!(selections(&buffer), vec![point(1, 12)]);
+}
+
+#[test]
+fn test_move_visual_keeps_sticky_col() {
+    let mut buffer = wrapped_buffer_with_selections(&vec![(1, 15, 1, 15, true)]);
+    buffer.move_visual_down(1, true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(1, 15, 2, 3, CursorDirection::Forward).with_sticky(5)]
+    );
+    buffer.move_visual_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(3, 5)]);
+}
+
+#[test]
+fn test_move_visual_without_wrap() {
+    let mut buffer = wrapped_buffer_with_selections(&vec![(1, 12, 1, 12, true)]);
+    buffer.unset_soft_wrap();
+    buffer.move_visual_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 3).with_sticky(12)]);
+    buffer.move_visual_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(3, 10).with_sticky(12)]);
+}
+
+#[test]
+fn test_move_visual_after_move_down() {
+    let text = "aaaa bbbb cccc dddd\nab\neeee ffff gggg hhhh";
+    let mut buffer = Buffer::from_reader(text.as_bytes()).unwrap();
+    buffer.set_soft_wrap(10, WrapMode::Char);
+    buffer.move_right(15, false);
+    buffer.move_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 3).with_sticky(16)]);
+    buffer.move_visual_down(1, false);
+    assert_eq!(selections(&buffer), vec![point(3, 16)]);
+    buffer.move_visual_up(2, false);
+    assert_eq!(selections(&buffer), vec![point(2, 3).with_sticky(6)]);
+    buffer.move_up(1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 6)]);
+}
@@ -0,0 +1,85 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::{Error, Regex};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_split_selections() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 1, 1, 17, false), (3, 1, 3, 4, true)]);
+    buffer
+        .split_selections(&Regex::new(r"\s+").unwrap())
+        .unwrap();
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 1, 1, 4, CursorDirection::Backward),
+            SelectionUnbound::new_quick(1, 6, 1, 9, CursorDirection::Backward),
+            SelectionUnbound::new_quick(1, 11, 1, 12, CursorDirection::Backward),
+            SelectionUnbound::new_quick(1, 14, 1, 17, CursorDirection::Backward),
+            SelectionUnbound::new_quick(3, 1, 3, 4, CursorDirection::Forward),
+        ]
+    );
+    assert_eq!(
+        buffer.selection_storage.main_selection(),
+        Some(&SelectionUnbound::new_quick(
+            3,
+            1,
+            3,
+            4,
+            CursorDirection::Forward
+        ))
+    );
+}
+
+#[test]
+fn test_split_selections_only_separators() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 5, 1, 5, true)]);
+    let selections_before = selections(&buffer);
+    assert!(matches!(
+        buffer.split_selections(&Regex::new(" ").unwrap()),
+        Err(Error::NothingSelected)
+    ));
+    assert_eq!(selections(&buffer), selections_before);
+}
+
+#[test]
+fn test_split_lines() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 50, 4, 3, true), (5, 3, 5, 6, false)]);
+    buffer.split_lines();
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 50, 1, 60, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 1, 2, 1, CursorDirection::Forward),
+            SelectionUnbound::new_quick(3, 1, 3, 21, CursorDirection::Forward),
+            SelectionUnbound::new_quick(4, 1, 4, 3, CursorDirection::Forward),
+            SelectionUnbound::new_quick(5, 3, 5, 6, CursorDirection::Backward),
+        ]
+    );
+    assert_eq!(
+        buffer.selection_storage.main_selection(),
+        Some(&SelectionUnbound::new_quick(
+            5,
+            3,
+            5,
+            6,
+            CursorDirection::Backward
+        ))
+    );
+}
+
+#[test]
+fn test_split_lines_keeps_empty_selections() {
+    let mut buffer = Buffer::empty();
+    buffer.split_lines();
+    assert_eq!(selections(&buffer), vec![point(1, 1)]);
+    buffer.rotate_main(1);
+    assert_eq!(buffer.main_selection().from().col(), 1);
+
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 2, 1, true)]);
+    buffer.move_to_buffer_end(false);
+    let selections_before = selections(&buffer);
+    buffer.split_lines();
+    assert_eq!(selections(&buffer), selections_before);
+    buffer.main_selection();
+}
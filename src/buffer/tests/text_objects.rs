@@ -0,0 +1,89 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::{ObjectScope, TextObject};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "if (a && (b || c)) {\n    run(\"fast\", x);\n}";
+
+#[test]
+fn test_select_inside_brackets() {
+    let mut buffer =
+        buffer_with_selections(TEXT, &vec![(1, 12, 1, 12, true), (2, 10, 2, 10, false)]);
+    buffer.select_object(TextObject::Brackets('(', ')'), ObjectScope::Inner);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 11, 1, 16, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 9, 2, 17, CursorDirection::Backward),
+        ]
+    );
+    buffer.select_object(TextObject::Brackets('(', ')'), ObjectScope::Around);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 10, 1, 17, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 8, 2, 18, CursorDirection::Backward),
+        ]
+    );
+    buffer.select_object(TextObject::Brackets('(', ')'), ObjectScope::Around);
+    assert_eq!(
+        selections(&buffer)[0],
+        SelectionUnbound::new_quick(1, 4, 1, 18, CursorDirection::Forward)
+    );
+}
+
+#[test]
+fn test_select_object_merges_selections() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 5, true), (1, 11, 1, 11, true)]);
+    buffer.select_object(TextObject::Brackets('(', ')'), ObjectScope::Around);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            4,
+            1,
+            18,
+            CursorDirection::Forward
+        )]
+    );
+}
+
+#[test]
+fn test_select_object_not_found() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true), (2, 12, 2, 12, true)]);
+    buffer.select_object(TextObject::Quotes('"'), ObjectScope::Around);
+    assert_eq!(
+        selections(&buffer),
+        vec![
+            SelectionUnbound::new_quick(1, 1, 1, 1, CursorDirection::Forward),
+            SelectionUnbound::new_quick(2, 9, 2, 14, CursorDirection::Forward),
+        ]
+    );
+}
+
+#[test]
+fn test_select_word_and_lines() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 6, 2, 6, true)]);
+    buffer.select_object(TextObject::Word, ObjectScope::Inner);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            2,
+            5,
+            2,
+            7,
+            CursorDirection::Forward
+        )]
+    );
+    buffer.select_object(TextObject::Paragraph, ObjectScope::Inner);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            1,
+            3,
+            1,
+            CursorDirection::Forward
+        )]
+    );
+}
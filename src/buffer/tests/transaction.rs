@@ -0,0 +1,131 @@
+use super::*;
+use crate::selections::{CursorDirection, PositionUnbound, SelectionUnbound};
+use crate::DeltaType;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_transaction_undone_at_once() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 3, true), (3, 11, 3, 11, true)]);
+    let selections_before = selections(&buffer);
+    let result: Result<_, ()> = buffer.transaction(|b| {
+        b.insert("top");
+        b.move_right(2, false);
+        b.insert("kek");
+        Ok(())
+    });
+    assert!(result.is_ok());
+    assert_eq!(buffer.current_revision(), 1);
+
+    buffer.undo();
+    assert_eq!(buffer, load_buffer());
+    assert_eq!(selections(&buffer), selections_before);
+
+    buffer.redo();
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 3, "top");
+    reference_buffer.insert_for_test(1, 8, "kek");
+    reference_buffer.insert_for_test(3, 11, "top");
+    reference_buffer.insert_for_test(3, 16, "kek");
+    assert_eq!(buffer, reference_buffer);
+}
+
+#[test]
+fn test_transaction_rollback() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 13, false), (3, 11, 3, 21, true)]);
+    let selections_before = selections(&buffer);
+    let result = buffer.transaction(|b| {
+        b.delete();
+        b.insert("kek\n");
+        b.move_down(2, true);
+        Err("nope")
+    });
+    assert_eq!(result, Err("nope"));
+    assert_eq!(buffer, load_buffer());
+    assert_eq!(selections(&buffer), selections_before);
+    assert!(buffer.undo().is_empty());
+}
+
+#[test]
+fn test_no_history_travel_within_transaction() {
+    let mut buffer = load_buffer();
+    buffer.insert("1");
+    let result: Result<_, ()> = buffer.transaction(|b| {
+        b.insert("x");
+        assert!(b.undo().is_empty());
+        assert!(b.redo().is_empty());
+        assert!(b.earlier(1).is_empty());
+        assert!(b.later(1).is_empty());
+        assert!(b.goto_revision(0).unwrap().is_empty());
+        b.insert("y");
+        Ok(())
+    });
+    assert!(result.is_ok());
+    assert_eq!(buffer.current_revision(), 2);
+    assert!(buffer.to_string().starts_with("1xy"));
+
+    buffer.undo();
+    assert!(buffer.to_string().starts_with("1"));
+    let _ = buffer.transaction(|b| -> Result<(), ()> {
+        b.insert("z");
+        assert!(b.redo().is_empty());
+        Err(())
+    });
+    assert!(buffer.to_string().starts_with("1"));
+}
+
+#[test]
+fn test_nested_transaction_rollback() {
+    let mut buffer = load_buffer();
+    let result: Result<_, ()> = buffer.transaction(|b| {
+        b.insert("top");
+        let inner = b.transaction(|b| {
+            b.insert("kek");
+            Err(())
+        });
+        assert!(inner.is_err());
+        b.insert("lol");
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 1, "toplol");
+    assert_eq!(buffer, reference_buffer);
+    assert_eq!(buffer.current_revision(), 1);
+}
+
+#[test]
+fn test_transaction_coalesced_deltas() {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 6, "kek");
+    let mut buffer = load_buffer();
+    let deltas: Vec<DeltaType> = buffer
+        .transaction(|b| -> Result<(), ()> {
+            b.move_right(5, false);
+            b.insert("kek");
+            b.move_left(1, false);
+            Ok(())
+        })
+        .unwrap()
+        .into_iter()
+        .map(|d| d.delta_type)
+        .collect();
+    assert_eq!(
+        deltas,
+        vec![
+            DeltaType::LineChanged {
+                idx: 1,
+                content: reference_buffer.rope.line(0)
+            },
+            DeltaType::SelectionDeleted {
+                identity: PositionUnbound {
+                    line: 1.into(),
+                    col: 1.into()
+                }
+            },
+            DeltaType::SelectionAdded {
+                selection: SelectionUnbound::new_quick(1, 8, 1, 8, CursorDirection::Forward)
+            },
+        ]
+    );
+}
@@ -0,0 +1,73 @@
+use super::*;
+use crate::selections::CursorDirection;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_undo_insert() {
+    let mut buffer = load_buffer_with_selections(&vec![
+        (1, 3, 1, 13, false),
+        (3, 11, 3, 21, true),
+        (4, 33, 4, 33, true),
+    ]);
+    let selections_before = selections(&buffer);
+    buffer.insert(" (top\nkek) ");
+    buffer.undo();
+    assert_eq!(buffer, load_buffer());
+    assert_eq!(selections(&buffer), selections_before);
+}
+
+#[test]
+fn test_undo_delete() {
+    let mut buffer =
+        load_buffer_with_selections(&vec![(1, 55, 4, 34, false), (4, 63, 6, 16, true)]);
+    let selections_before = selections(&buffer);
+    buffer.delete();
+    buffer.undo();
+    assert_eq!(buffer, load_buffer());
+    assert_eq!(selections(&buffer), selections_before);
+    assert_eq!(
+        selections(&buffer)[0].cursor_direction,
+        CursorDirection::Backward
+    );
+}
+
+#[test]
+fn test_redo() {
+    let mut buffer = load_buffer_with_selections(&vec![(1, 3, 1, 3, true), (3, 11, 3, 11, true)]);
+    buffer.insert("kek");
+    let selections_after = selections(&buffer);
+    buffer.delete();
+    buffer.undo();
+    buffer.undo();
+    buffer.redo();
+
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 3, "kek");
+    reference_buffer.insert_for_test(3, 11, "kek");
+    assert_eq!(buffer, reference_buffer);
+    assert_eq!(selections(&buffer), selections_after);
+}
+
+#[test]
+fn test_undo_redo_bounds() {
+    let mut buffer = load_buffer();
+    assert!(buffer.undo().is_empty());
+    buffer.insert("kek");
+    assert!(buffer.redo().is_empty());
+    buffer.undo();
+    assert!(buffer.undo().is_empty());
+    assert_eq!(buffer, load_buffer());
+}
+
+#[test]
+fn test_edit_drops_redo() {
+    let mut buffer = load_buffer();
+    buffer.insert("top");
+    buffer.undo();
+    buffer.insert("kek");
+    assert!(buffer.redo().is_empty());
+
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 1, "kek");
+    assert_eq!(buffer, reference_buffer);
+}
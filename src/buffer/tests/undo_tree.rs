@@ -0,0 +1,110 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::Error;
+use pretty_assertions::assert_eq;
+use std::time::{Duration, Instant};
+
+fn reference_with(text: &str) -> Buffer {
+    let mut reference_buffer = load_buffer();
+    reference_buffer.insert_for_test(1, 1, text);
+    reference_buffer
+}
+
+#[test]
+fn test_edit_after_undo_creates_branch() {
+    let mut buffer = load_buffer();
+    buffer.insert("top");
+    buffer.undo();
+    buffer.insert("kek");
+    assert_eq!(buffer.current_revision(), 2);
+    assert_eq!(buffer.branches(), vec![1, 2]);
+
+    buffer.goto_revision(1).unwrap();
+    assert_eq!(buffer, reference_with("top"));
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            4,
+            1,
+            4,
+            CursorDirection::Forward
+        )]
+    );
+}
+
+#[test]
+fn test_goto_revision_across_branches() {
+    let mut buffer = load_buffer();
+    buffer.insert("a");
+    buffer.insert("b");
+    buffer.undo();
+    buffer.insert("c");
+    buffer.insert("d");
+
+    buffer.goto_revision(2).unwrap();
+    assert_eq!(buffer, reference_with("ab"));
+    buffer.goto_revision(4).unwrap();
+    assert_eq!(buffer, reference_with("acd"));
+    buffer.goto_revision(0).unwrap();
+    assert_eq!(buffer, load_buffer());
+    assert!(matches!(
+        buffer.goto_revision(5),
+        Err(Error::RevisionNotFound(5))
+    ));
+}
+
+#[test]
+fn test_redo_follows_last_visited_branch() {
+    let mut buffer = load_buffer();
+    buffer.insert("a");
+    buffer.undo();
+    buffer.insert("b");
+    buffer.goto_revision(1).unwrap();
+    buffer.undo();
+    buffer.redo();
+    assert_eq!(buffer, reference_with("a"));
+}
+
+#[test]
+fn test_earlier_later_by_steps() {
+    let mut buffer = load_buffer();
+    buffer.insert("a");
+    buffer.undo();
+    buffer.insert("b");
+    buffer.insert("c");
+
+    buffer.earlier(2);
+    assert_eq!(buffer.current_revision(), 1);
+    assert_eq!(buffer, reference_with("a"));
+    buffer.later(1);
+    assert_eq!(buffer, reference_with("b"));
+    buffer.later(10);
+    assert_eq!(buffer, reference_with("bc"));
+    buffer.earlier(10);
+    assert_eq!(buffer, load_buffer());
+}
+
+#[test]
+fn test_earlier_later_by_duration() {
+    let mut buffer = load_buffer();
+    buffer.insert("a");
+    buffer.insert("b");
+    buffer.insert("c");
+    let start = Instant::now();
+    for (idx, secs) in [(0, 0), (1, 10), (2, 20), (3, 60)] {
+        let timestamp = start + Duration::from_secs(secs);
+        buffer.history.set_timestamp(idx, timestamp);
+    }
+
+    buffer.earlier_by(Duration::from_secs(35));
+    assert_eq!(buffer, reference_with("ab"));
+    buffer.earlier_by(Duration::from_secs(5));
+    assert_eq!(buffer, reference_with("a"));
+    buffer.later_by(Duration::from_secs(15));
+    assert_eq!(buffer, reference_with("ab"));
+    buffer.later_by(Duration::from_secs(100));
+    assert_eq!(buffer, reference_with("abc"));
+    buffer.earlier_by(Duration::from_secs(100));
+    assert_eq!(buffer, load_buffer());
+}
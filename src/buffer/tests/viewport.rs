@@ -0,0 +1,123 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use crate::Viewport;
+use pretty_assertions::assert_eq;
+
+fn text() -> String {
+    (1..=30)
+        .map(|line| format!("line {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn top_line(buffer: &Buffer) -> usize {
+    buffer.viewport().unwrap().top_line
+}
+
+#[test]
+fn test_set_viewport_scrolls_to_main() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(20, 1, 20, 1, true)]);
+    buffer.set_viewport(Some(Viewport::new(5, 10).with_scrolloff(1)));
+    assert_eq!(buffer.viewport().unwrap().lines(), 17..=21);
+}
+
+#[test]
+fn test_movements_scroll() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(1, 1, 1, 1, true)]);
+    buffer.set_viewport(Some(Viewport::new(5, 10).with_scrolloff(1)));
+    buffer.move_down(4, false);
+    assert_eq!(top_line(&buffer), 2);
+    buffer.goto_line(30, false);
+    assert_eq!(top_line(&buffer), 26);
+    buffer.move_up(3, false);
+    assert_eq!(top_line(&buffer), 26);
+    buffer.move_to_buffer_start(false);
+    assert_eq!(top_line(&buffer), 1);
+}
+
+#[test]
+fn test_edits_scroll() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(5, 1, 5, 1, true)]);
+    buffer.set_viewport(Some(Viewport::new(5, 10)));
+    buffer.insert("a\nb\nc\n");
+    assert_eq!(buffer.viewport().unwrap().lines(), 4..=8);
+    buffer.undo();
+    assert_eq!(buffer.viewport().unwrap().lines(), 4..=8);
+    buffer.goto_line(1, false);
+    assert_eq!(top_line(&buffer), 1);
+}
+
+#[test]
+fn test_columns_scroll() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(1, 1, 1, 1, true)]);
+    buffer.set_viewport(Some(Viewport::new(5, 4)));
+    buffer.move_to_line_end(false);
+    assert_eq!(buffer.viewport().unwrap().left_col, 3);
+    buffer.move_left(4, false);
+    assert_eq!(buffer.viewport().unwrap().left_col, 2);
+    buffer.set_soft_wrap(4, crate::WrapMode::Char);
+    buffer.move_to_line_end(false);
+    assert_eq!(buffer.viewport().unwrap().left_col, 0);
+}
+
+#[test]
+fn test_pages() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(1, 3, 1, 3, true)]);
+    buffer.set_viewport(Some(Viewport::new(10, 10)));
+    buffer.page_down(false);
+    assert_eq!(top_line(&buffer), 11);
+    assert_eq!(selections(&buffer), vec![point(11, 3)]);
+    buffer.half_page_down(false);
+    assert_eq!(top_line(&buffer), 16);
+    assert_eq!(selections(&buffer), vec![point(16, 3)]);
+    buffer.page_up(true);
+    assert_eq!(top_line(&buffer), 6);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            6,
+            3,
+            16,
+            3,
+            CursorDirection::Backward
+        )]
+    );
+    buffer.half_page_up(false);
+    assert_eq!(top_line(&buffer), 1);
+    assert_eq!(selections(&buffer), vec![point(1, 3)]);
+    buffer.page_down(false);
+    buffer.page_down(false);
+    buffer.page_down(false);
+    assert_eq!(buffer.viewport().unwrap().lines(), 21..=30);
+    assert_eq!(selections(&buffer), vec![point(30, 3)]);
+}
+
+#[test]
+fn test_pages_without_viewport() {
+    let mut buffer = buffer_with_selections(&text(), &vec![(1, 3, 1, 3, true)]);
+    assert!(buffer.page_down(false).is_empty());
+    assert_eq!(selections(&buffer), vec![point(1, 3)]);
+}
+
+#[test]
+fn test_visible_selections() {
+    let mut buffer = buffer_with_selections(
+        &text(),
+        &vec![
+            (1, 1, 1, 2, true),
+            (5, 1, 12, 2, true),
+            (15, 1, 15, 1, true),
+            (25, 1, 25, 1, true),
+        ],
+    );
+    assert_eq!(buffer.visible_selections().count(), 4);
+    buffer.viewport = Some(Viewport {
+        top_line: 10,
+        ..Viewport::new(10, 10)
+    });
+    let lines: Vec<usize> = buffer
+        .visible_selections()
+        .map(|s| s.from().line())
+        .collect();
+    assert_eq!(lines, vec![5, 15]);
+}
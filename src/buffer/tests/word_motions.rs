@@ -0,0 +1,89 @@
+use super::*;
+use crate::selections::{CursorDirection, SelectionUnbound};
+use pretty_assertions::assert_eq;
+
+const TEXT: &str = "foo-bar baz.qux\n  kebab-case words";
+
+#[test]
+fn test_move_word_forward() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true)]);
+    let mut cursors = vec![];
+    for _ in 0..7 {
+        buffer.move_word_forward(1, false);
+        cursors.extend(selections(&buffer));
+    }
+    assert_eq!(
+        cursors,
+        vec![
+            point(1, 4),
+            point(1, 5),
+            point(1, 9),
+            point(1, 12),
+            point(1, 13),
+            point(2, 3),
+            point(2, 8),
+        ]
+    );
+}
+
+#[test]
+fn test_move_word_backward_and_end() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 3, 2, 3, true)]);
+    buffer.move_word_backward(2, false);
+    assert_eq!(selections(&buffer), vec![point(1, 12)]);
+    buffer.move_word_end(3, false);
+    assert_eq!(selections(&buffer), vec![point(2, 8)]);
+}
+
+#[test]
+fn test_move_word_extend() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 5, 1, 5, true)]);
+    buffer.move_word_end(2, true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            5,
+            1,
+            11,
+            CursorDirection::Forward
+        )]
+    );
+    buffer.move_word_backward(3, true);
+    assert_eq!(
+        selections(&buffer),
+        vec![SelectionUnbound::new_quick(
+            1,
+            4,
+            1,
+            5,
+            CursorDirection::Backward
+        )]
+    );
+}
+
+#[test]
+fn test_move_big_words() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true)]);
+    buffer.move_big_word_forward(2, false);
+    assert_eq!(selections(&buffer), vec![point(2, 3)]);
+    buffer.move_big_word_end(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 12)]);
+    buffer.move_big_word_backward(2, false);
+    assert_eq!(selections(&buffer), vec![point(1, 9)]);
+}
+
+#[test]
+fn test_custom_word_chars() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(2, 3, 2, 3, true)]);
+    buffer.set_word_chars(|c| c.is_alphanumeric() || c == '-');
+    buffer.move_word_end(1, false);
+    assert_eq!(selections(&buffer), vec![point(2, 12)]);
+}
+
+#[test]
+fn test_move_word_merges_selections() {
+    let mut buffer = buffer_with_selections(TEXT, &vec![(1, 1, 1, 1, true), (1, 2, 1, 2, true)]);
+    buffer.move_word_forward(1, false);
+    assert_eq!(selections(&buffer), vec![point(1, 4)]);
+}
@@ -0,0 +1,465 @@
+//! Changes of a text as a sequence of operations over chars which could
+//! be applied, inverted, composed and sent elsewhere to replay them
+use crate::selections::{PositionUnbound, SelectionUnbound};
+use ropey::Rope;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A step of changes walking through a text from its beginning
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Operation {
+    /// Keep `n` chars as they are
+    Retain(usize),
+    /// Insert text at the current point
+    Insert(String),
+    /// Delete `n` chars starting from the current point
+    Delete(usize),
+}
+
+/// Where a position on the insertion point goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// Stay in front of inserted text
+    Before,
+    /// Move after inserted text
+    After,
+}
+
+/// Changes which transform a text of one length into another one.
+///
+/// Operations cover the whole text, so chars retained and deleted sum up
+/// to the length of the text the changes apply to. Adjacent operations
+/// of the same kind are merged and an insertion always goes before
+/// a deletion at the same point, so equal changes have equal operations.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChangeSet {
+    operations: Vec<Operation>,
+}
+
+impl ChangeSet {
+    /// Create empty changes to build them with `retain`, `insert` and
+    /// `delete` calls
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Changes keeping a text of `len` chars as it is
+    pub fn identity(len: usize) -> Self {
+        let mut changes = ChangeSet::new();
+        changes.retain(len);
+        changes
+    }
+
+    /// Build changes from operations merging adjacent ones if needed
+    pub fn from_operations(operations: impl IntoIterator<Item = Operation>) -> Self {
+        let mut changes = ChangeSet::new();
+        for operation in operations {
+            match operation {
+                Operation::Retain(n) => changes.retain(n),
+                Operation::Insert(text) => changes.insert(&text),
+                Operation::Delete(n) => changes.delete(n),
+            }
+        }
+        changes
+    }
+
+    /// Get operations
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Length in chars of a text the changes apply to
+    pub fn len_before(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Insert(_) => 0,
+                operation => operation_len(operation),
+            })
+            .sum()
+    }
+
+    /// Length in chars of a text after the changes
+    pub fn len_after(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Delete(_) => 0,
+                operation => operation_len(operation),
+            })
+            .sum()
+    }
+
+    /// Check if the changes keep a text as it is
+    pub fn is_empty(&self) -> bool {
+        self.operations
+            .iter()
+            .all(|operation| matches!(operation, Operation::Retain(_)))
+    }
+
+    /// Keep next `n` chars
+    pub fn retain(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        match self.operations.last_mut() {
+            Some(Operation::Retain(m)) => *m += n,
+            _ => self.operations.push(Operation::Retain(n)),
+        }
+    }
+
+    /// Insert `text` at the current point
+    pub fn insert(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.operations.as_mut_slice() {
+            [.., Operation::Insert(previous)]
+            | [.., Operation::Insert(previous), Operation::Delete(_)] => previous.push_str(text),
+            [.., Operation::Delete(_)] => {
+                let delete = self.operations.pop();
+                self.operations.push(Operation::Insert(text.to_owned()));
+                self.operations.extend(delete);
+            }
+            _ => self.operations.push(Operation::Insert(text.to_owned())),
+        }
+    }
+
+    /// Delete next `n` chars
+    pub fn delete(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        match self.operations.last_mut() {
+            Some(Operation::Delete(m)) => *m += n,
+            _ => self.operations.push(Operation::Delete(n)),
+        }
+    }
+
+    /// Perform the changes on the rope.
+    ///
+    /// Panics if the rope's length doesn't match the changes.
+    pub fn apply(&self, rope: &mut Rope) {
+        self.apply_with(rope, |_, _, _| {});
+    }
+
+    /// Perform the changes on the rope calling `on_edit` after each
+    /// insertion and deletion with the rope, the line where the edit
+    /// started (indexed from 1) and the count of lines before the edit
+    pub(crate) fn apply_with<F>(&self, rope: &mut Rope, mut on_edit: F)
+    where
+        F: FnMut(&Rope, usize, usize),
+    {
+        assert_eq!(
+            self.len_before(),
+            rope.len_chars(),
+            "Changes are made for a text of another length"
+        );
+        let mut char_idx = 0;
+        for operation in self.operations.iter() {
+            match operation {
+                Operation::Retain(n) => char_idx += n,
+                Operation::Insert(text) => {
+                    let line = rope.char_to_line(char_idx) + 1;
+                    let lines_before = rope.len_lines();
+                    rope.insert(char_idx, text);
+                    char_idx += text.chars().count();
+                    on_edit(rope, line, lines_before);
+                }
+                Operation::Delete(n) => {
+                    let line = rope.char_to_line(char_idx) + 1;
+                    let lines_before = rope.len_lines();
+                    rope.remove(char_idx..char_idx + n);
+                    on_edit(rope, line, lines_before);
+                }
+            }
+        }
+    }
+
+    /// Build changes which revert these ones; `original` is the text
+    /// before the changes, as deleted text is needed to restore it.
+    pub fn invert(&self, original: &Rope) -> ChangeSet {
+        let mut inverse = ChangeSet::new();
+        let mut char_idx = 0;
+        for operation in self.operations.iter() {
+            match operation {
+                Operation::Retain(n) => {
+                    inverse.retain(*n);
+                    char_idx += n;
+                }
+                Operation::Insert(text) => inverse.delete(text.chars().count()),
+                Operation::Delete(n) => {
+                    inverse.insert(&original.slice(char_idx..char_idx + n).to_string());
+                    char_idx += n;
+                }
+            }
+        }
+        inverse
+    }
+
+    /// Combine these changes with `other` ones made right after them
+    /// into changes doing both at once.
+    ///
+    /// Panics if `other` changes are made for a text of another length.
+    pub fn compose(&self, other: &ChangeSet) -> ChangeSet {
+        assert_eq!(
+            self.len_after(),
+            other.len_before(),
+            "Changes are made for a text of another length"
+        );
+        let mut composed = ChangeSet::new();
+        let mut first_iter = self.operations.iter().cloned();
+        let mut second_iter = other.operations.iter().cloned();
+        let mut first = first_iter.next();
+        let mut second = second_iter.next();
+        loop {
+            match (first.take(), second.take()) {
+                (None, None) => break,
+                // Deleted chars are not seen by the second changes
+                (Some(Operation::Delete(n)), s) => {
+                    composed.delete(n);
+                    first = first_iter.next();
+                    second = s;
+                }
+                // Inserted by the second changes are not seen by the first ones
+                (f, Some(Operation::Insert(text))) => {
+                    composed.insert(&text);
+                    first = f;
+                    second = second_iter.next();
+                }
+                (Some(f), Some(s)) => {
+                    let n = operation_len(&f).min(operation_len(&s));
+                    let (f_head, f_rest) = split_operation(f, n);
+                    let (s_head, s_rest) = split_operation(s, n);
+                    match (f_head, s_head) {
+                        (Operation::Retain(n), Operation::Retain(_)) => composed.retain(n),
+                        (Operation::Retain(n), Operation::Delete(_)) => composed.delete(n),
+                        (Operation::Insert(text), Operation::Retain(_)) => composed.insert(&text),
+                        // Inserted text was deleted right away
+                        _ => {}
+                    }
+                    first = f_rest.or_else(|| first_iter.next());
+                    second = s_rest.or_else(|| second_iter.next());
+                }
+                _ => unreachable!("Lengths of changes were checked"),
+            }
+        }
+        composed
+    }
+
+    /// Find where a char with index `char_idx` goes after the changes.
+    /// Deleted chars collapse to the deletion point.
+    pub fn map_char_idx(&self, char_idx: usize, assoc: Assoc) -> usize {
+        let mut old_idx = 0;
+        let mut new_idx = 0;
+        for operation in self.operations.iter() {
+            match operation {
+                Operation::Retain(n) => {
+                    if char_idx < old_idx + n {
+                        return new_idx + char_idx - old_idx;
+                    }
+                    old_idx += n;
+                    new_idx += n;
+                }
+                Operation::Insert(text) => {
+                    if char_idx == old_idx && assoc == Assoc::Before {
+                        return new_idx;
+                    }
+                    new_idx += text.chars().count();
+                }
+                Operation::Delete(n) => {
+                    if char_idx < old_idx + n {
+                        return new_idx;
+                    }
+                    old_idx += n;
+                }
+            }
+        }
+        new_idx + char_idx.saturating_sub(old_idx)
+    }
+
+    /// Find where a position of the text `before` goes in the text `after`
+    /// the changes
+    pub fn map_position(
+        &self,
+        position: PositionUnbound,
+        assoc: Assoc,
+        before: &Rope,
+        after: &Rope,
+    ) -> PositionUnbound {
+        let char_idx = self.map_char_idx(position.to_char_idx(before), assoc);
+        PositionUnbound::from_char_idx(after, char_idx)
+    }
+
+    /// Move a selection of the text `before` along with its text to
+    /// the text `after` the changes
+    pub fn map_selection(
+        &self,
+        selection: &SelectionUnbound,
+        assoc: Assoc,
+        before: &Rope,
+        after: &Rope,
+    ) -> SelectionUnbound {
+        let mut mapped = selection.clone();
+        mapped.from = self.map_position(selection.from, assoc, before, after);
+        mapped.to = self.map_position(selection.to, assoc, before, after);
+        mapped
+    }
+}
+
+/// Length of an operation in chars
+fn operation_len(operation: &Operation) -> usize {
+    match operation {
+        Operation::Retain(n) | Operation::Delete(n) => *n,
+        Operation::Insert(text) => text.chars().count(),
+    }
+}
+
+/// Split an operation into the part covering `n` chars and the rest if any
+fn split_operation(operation: Operation, n: usize) -> (Operation, Option<Operation>) {
+    match operation {
+        Operation::Retain(m) if m > n => (Operation::Retain(n), Some(Operation::Retain(m - n))),
+        Operation::Delete(m) if m > n => (Operation::Delete(n), Some(Operation::Delete(m - n))),
+        Operation::Insert(text) if text.chars().count() > n => {
+            let byte_idx = text.char_indices().nth(n).map(|(i, _)| i).unwrap();
+            let (head, rest) = text.split_at(byte_idx);
+            (
+                Operation::Insert(head.to_owned()),
+                Some(Operation::Insert(rest.to_owned())),
+            )
+        }
+        operation => (operation, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(operations: &[Operation]) -> ChangeSet {
+        ChangeSet::from_operations(operations.iter().cloned())
+    }
+
+    #[test]
+    fn test_canonical_operations() {
+        let mut changes = ChangeSet::new();
+        changes.retain(2);
+        changes.retain(0);
+        changes.retain(1);
+        changes.delete(2);
+        changes.insert("ab");
+        changes.insert("c");
+        changes.retain(1);
+        assert_eq!(
+            changes.operations(),
+            &[
+                Operation::Retain(3),
+                Operation::Insert("abc".to_owned()),
+                Operation::Delete(2),
+                Operation::Retain(1),
+            ]
+        );
+        assert_eq!(changes.len_before(), 6);
+        assert_eq!(changes.len_after(), 7);
+        assert!(!changes.is_empty());
+        assert!(ChangeSet::identity(6).is_empty());
+    }
+
+    #[test]
+    fn test_apply_and_invert() {
+        let original = Rope::from_str("hello world");
+        let changes = changes(&[
+            Operation::Insert("oh, ".to_owned()),
+            Operation::Retain(5),
+            Operation::Delete(6),
+            Operation::Insert("!".to_owned()),
+        ]);
+        let mut rope = original.clone();
+        changes.apply(&mut rope);
+        assert_eq!(rope.to_string(), "oh, hello!");
+
+        changes.invert(&original).apply(&mut rope);
+        assert_eq!(rope, original);
+    }
+
+    #[test]
+    fn test_compose() {
+        let original = Rope::from_str("hello world");
+        let first = changes(&[
+            Operation::Retain(6),
+            Operation::Delete(5),
+            Operation::Insert("there".to_owned()),
+        ]);
+        let second = changes(&[
+            Operation::Insert("well, ".to_owned()),
+            Operation::Retain(8),
+            Operation::Delete(3),
+            Operation::Insert("at".to_owned()),
+        ]);
+        let composed = first.compose(&second);
+
+        let mut expected = original.clone();
+        first.apply(&mut expected);
+        second.apply(&mut expected);
+        assert_eq!(expected.to_string(), "well, hello that");
+
+        let mut rope = original.clone();
+        composed.apply(&mut rope);
+        assert_eq!(rope, expected);
+        assert_eq!(
+            composed.operations(),
+            &[
+                Operation::Insert("well, ".to_owned()),
+                Operation::Retain(6),
+                Operation::Insert("that".to_owned()),
+                Operation::Delete(5),
+            ]
+        );
+
+        let inverse = second.invert(&Rope::from_str("hello there"));
+        let inverse = inverse.compose(&first.invert(&original));
+        inverse.apply(&mut rope);
+        assert_eq!(rope, original);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compose_length_mismatch() {
+        ChangeSet::identity(3).compose(&ChangeSet::identity(4));
+    }
+
+    #[test]
+    fn test_map_char_idx() {
+        let changes = changes(&[
+            Operation::Retain(2),
+            Operation::Insert("abc".to_owned()),
+            Operation::Retain(2),
+            Operation::Delete(3),
+            Operation::Retain(1),
+        ]);
+        assert_eq!(changes.map_char_idx(1, Assoc::After), 1);
+        assert_eq!(changes.map_char_idx(2, Assoc::Before), 2);
+        assert_eq!(changes.map_char_idx(2, Assoc::After), 5);
+        assert_eq!(changes.map_char_idx(3, Assoc::Before), 6);
+        assert_eq!(changes.map_char_idx(4, Assoc::After), 7);
+        assert_eq!(changes.map_char_idx(6, Assoc::After), 7);
+        assert_eq!(changes.map_char_idx(7, Assoc::After), 7);
+        assert_eq!(changes.map_char_idx(8, Assoc::After), 8);
+    }
+
+    #[test]
+    fn test_map_selection() {
+        let before = Rope::from_str("ab\ncd");
+        let changes = changes(&[Operation::Insert("x\n".to_owned()), Operation::Retain(5)]);
+        let mut after = before.clone();
+        changes.apply(&mut after);
+
+        let selection = SelectionUnbound::new_quick(1, 2, 2, 1, Default::default());
+        assert_eq!(
+            changes.map_selection(&selection, Assoc::After, &before, &after),
+            SelectionUnbound::new_quick(2, 2, 3, 1, Default::default())
+        );
+    }
+}
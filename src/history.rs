@@ -0,0 +1,283 @@
+//! Edits history which makes buffer changes revertible
+use crate::changes::ChangeSet;
+use crate::selections::storage::SelectionStorage;
+use crate::text::LineEnding;
+use std::time::{Duration, Instant};
+
+/// A single undoable change: changes of the text, changes reverting them
+/// and the state of selections and the line ending around them
+#[derive(Debug)]
+pub(crate) struct Revision {
+    pub(crate) changes: ChangeSet,
+    pub(crate) inverse: ChangeSet,
+    pub(crate) selections_before: SelectionStorage,
+    pub(crate) selections_after: SelectionStorage,
+    /// Line ending the buffer uses after the revision
+    line_ending: LineEnding,
+    /// Revision this one was applied on top of; the root revision
+    /// points to itself
+    parent: usize,
+    /// Child revision which was visited last, redo follows it
+    last_child: Option<usize>,
+    /// When the revision was committed
+    timestamp: Instant,
+}
+
+/// A move across the history tree
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Step {
+    /// Revert the revision with the index
+    Undo(usize),
+    /// Apply the revision with the index
+    Redo(usize),
+}
+
+/// History of buffer changes organized as a tree.
+///
+/// Making an edit after undo doesn't discard undone revisions but starts
+/// a new branch instead. Revisions are indexed in order of creation, the
+/// root revision `0` represents the buffer's initial state.
+#[derive(Debug)]
+pub(crate) struct History {
+    revisions: Vec<Revision>,
+    /// Index of the revision the buffer is at
+    current: usize,
+    /// Stack of open groups, nested ones are on top
+    groups: Vec<Group>,
+}
+
+/// Changes composed to be committed as a single revision
+#[derive(Debug)]
+pub(crate) struct Group {
+    pub(crate) changes: ChangeSet,
+    pub(crate) inverse: ChangeSet,
+    pub(crate) selections_before: SelectionStorage,
+    pub(crate) line_ending_before: LineEnding,
+}
+
+impl History {
+    /// Create history of a buffer using `line_ending` initially
+    pub(crate) fn new(line_ending: LineEnding) -> Self {
+        History {
+            revisions: vec![Revision {
+                changes: ChangeSet::new(),
+                inverse: ChangeSet::new(),
+                selections_before: SelectionStorage::new(),
+                selections_after: SelectionStorage::new(),
+                line_ending,
+                parent: 0,
+                last_child: None,
+                timestamp: Instant::now(),
+            }],
+            current: 0,
+            groups: vec![],
+        }
+    }
+
+    /// Record a change as a child of the current revision; nothing is
+    /// recorded if the text stays the same.
+    ///
+    /// If a group is open, changes are composed with its ones instead.
+    pub(crate) fn commit(
+        &mut self,
+        changes: ChangeSet,
+        inverse: ChangeSet,
+        selections_before: SelectionStorage,
+        selections_after: SelectionStorage,
+        line_ending: LineEnding,
+    ) {
+        // A change of the line ending alone is still worth a revision
+        if changes.is_empty() && line_ending == self.revisions[self.current].line_ending {
+            return;
+        }
+        if let Some(group) = self.groups.last_mut() {
+            group.changes = group.changes.compose(&changes);
+            group.inverse = inverse.compose(&group.inverse);
+            return;
+        }
+        let idx = self.revisions.len();
+        self.revisions.push(Revision {
+            changes,
+            inverse,
+            selections_before,
+            selections_after,
+            line_ending,
+            parent: self.current,
+            last_child: None,
+            timestamp: Instant::now(),
+        });
+        self.revisions[self.current].last_child = Some(idx);
+        self.current = idx;
+    }
+
+    /// Start collecting changes of a text of `len` chars to commit them
+    /// later as one revision. Groups could be nested, then an inner group
+    /// becomes a part of the outer one.
+    pub(crate) fn begin_group(
+        &mut self,
+        len: usize,
+        selections_before: SelectionStorage,
+        line_ending_before: LineEnding,
+    ) {
+        self.groups.push(Group {
+            changes: ChangeSet::identity(len),
+            inverse: ChangeSet::identity(len),
+            selections_before,
+            line_ending_before,
+        });
+    }
+
+    /// Close the innermost group committing its changes, which are
+    /// returned too
+    pub(crate) fn end_group(
+        &mut self,
+        selections_after: SelectionStorage,
+        line_ending_after: LineEnding,
+    ) -> Option<ChangeSet> {
+        let group = self.groups.pop()?;
+        let changes = group.changes.clone();
+        self.commit(
+            group.changes,
+            group.inverse,
+            group.selections_before,
+            selections_after,
+            line_ending_after,
+        );
+        Some(changes)
+    }
+
+    /// Close the innermost group returning its changes to be reverted
+    pub(crate) fn abort_group(&mut self) -> Option<Group> {
+        self.groups.pop()
+    }
+
+    /// Get revision by its index
+    pub(crate) fn revision(&self, idx: usize) -> &Revision {
+        &self.revisions[idx]
+    }
+
+    /// Get the line ending used after the revision with the index
+    pub(crate) fn line_ending_after(&self, idx: usize) -> LineEnding {
+        self.revisions[idx].line_ending
+    }
+
+    /// Get the line ending used before the revision with the index
+    pub(crate) fn line_ending_before(&self, idx: usize) -> LineEnding {
+        self.line_ending_after(self.revisions[idx].parent)
+    }
+
+    /// Index of the revision the buffer is at
+    pub(crate) fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Check if there is a revision with the index
+    pub(crate) fn contains(&self, idx: usize) -> bool {
+        idx < self.revisions.len()
+    }
+
+    /// Revisions which have no children, in order of creation
+    pub(crate) fn leaves(&self) -> Vec<usize> {
+        (0..self.revisions.len())
+            .filter(|&idx| self.revisions[idx].last_child.is_none())
+            .collect()
+    }
+
+    /// Step back to the parent revision
+    pub(crate) fn undo(&mut self) -> Vec<Step> {
+        let parent = self.revisions[self.current].parent;
+        self.goto(parent)
+    }
+
+    /// Step forward to the last visited child revision
+    pub(crate) fn redo(&mut self) -> Vec<Step> {
+        match self.revisions[self.current].last_child {
+            Some(child) => self.goto(child),
+            None => vec![],
+        }
+    }
+
+    /// Move `n` revisions back in order of creation
+    pub(crate) fn earlier(&mut self, n: usize) -> Vec<Step> {
+        self.goto(self.current.saturating_sub(n))
+    }
+
+    /// Move `n` revisions forward in order of creation
+    pub(crate) fn later(&mut self, n: usize) -> Vec<Step> {
+        let last = self.revisions.len() - 1;
+        self.goto(last.min(self.current.saturating_add(n)))
+    }
+
+    /// Move to the state the buffer had `duration` before the current
+    /// revision was committed
+    pub(crate) fn earlier_by(&mut self, duration: Duration) -> Vec<Step> {
+        let target = self.revisions[self.current]
+            .timestamp
+            .checked_sub(duration)
+            .map(|t| self.last_committed_until(t))
+            .unwrap_or(0);
+        self.goto(target)
+    }
+
+    /// Move to the state the buffer had `duration` after the current
+    /// revision was committed
+    pub(crate) fn later_by(&mut self, duration: Duration) -> Vec<Step> {
+        let target = self.revisions[self.current]
+            .timestamp
+            .checked_add(duration)
+            .map(|t| self.last_committed_until(t))
+            .unwrap_or(self.revisions.len() - 1);
+        self.goto(target.max(self.current))
+    }
+
+    /// Find the latest revision committed not after `time`
+    fn last_committed_until(&self, time: Instant) -> usize {
+        self.revisions
+            .iter()
+            .rposition(|r| r.timestamp <= time)
+            .unwrap_or(0)
+    }
+
+    /// Move to the revision `target`, returning steps required to
+    /// transform the current state into the target one: reverting
+    /// revisions up to the common ancestor and then applying revisions
+    /// down to the target.
+    pub(crate) fn goto(&mut self, target: usize) -> Vec<Step> {
+        let current_ancestors = self.ancestors(self.current);
+        let mut redo_path = vec![];
+        let mut common = target;
+        while !current_ancestors.contains(&common) {
+            redo_path.push(common);
+            common = self.revisions[common].parent;
+        }
+
+        let mut steps: Vec<Step> = current_ancestors
+            .into_iter()
+            .take_while(|&idx| idx != common)
+            .map(Step::Undo)
+            .collect();
+        for &idx in redo_path.iter().rev() {
+            let parent = self.revisions[idx].parent;
+            self.revisions[parent].last_child = Some(idx);
+            steps.push(Step::Redo(idx));
+        }
+        self.current = target;
+        steps
+    }
+
+    /// Revision's path to the root, starting with the revision itself
+    fn ancestors(&self, mut idx: usize) -> Vec<usize> {
+        let mut ancestors = vec![idx];
+        while idx != 0 {
+            idx = self.revisions[idx].parent;
+            ancestors.push(idx);
+        }
+        ancestors
+    }
+
+    /// Override revision's commit time
+    #[cfg(test)]
+    pub(crate) fn set_timestamp(&mut self, idx: usize, timestamp: Instant) {
+        self.revisions[idx].timestamp = timestamp;
+    }
+}
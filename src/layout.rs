@@ -0,0 +1,266 @@
+//! Soft wrap of long lines into visual rows. Rows are cached per line and
+//! updated only for lines affected by edits.
+use crate::selections::PositionUnbound;
+use crate::text::{
+    col_at_display_col, display_col, grapheme_width, line_content_end, next_grapheme_boundary,
+};
+use crate::{DeltaType, LineLength};
+use ropey::Rope;
+use std::borrow::Cow;
+
+/// Where long lines could be wrapped
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WrapMode {
+    /// Between any grapheme clusters
+    Char,
+    /// After whitespace, unless a word doesn't fit a row by itself
+    Word,
+}
+
+/// A row of the screen a part of a line is displayed on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct VisualRow {
+    /// Line the row belongs to
+    pub line: usize,
+    /// Column of the row's first grapheme cluster
+    pub start: usize,
+    /// Column right after the row's last grapheme cluster; the last row of
+    /// a line includes its line break
+    pub end: usize,
+}
+
+/// Visual rows of the text's lines
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    /// Width of rows and wrap mode if lines are wrapped
+    wrap: Option<(usize, WrapMode)>,
+    tab_width: usize,
+    /// Columns rows start at for each line, except the first row
+    breaks: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    /// Build the layout wrapping lines of the whole `rope`
+    pub(crate) fn new(rope: &Rope, wrap: Option<(usize, WrapMode)>, tab_width: usize) -> Self {
+        let mut layout = Layout {
+            wrap,
+            tab_width,
+            breaks: vec![],
+        };
+        if wrap.is_some() {
+            layout.breaks = (0..rope.len_lines())
+                .map(|line_idx| layout.wrap_line(rope, line_idx))
+                .collect();
+        }
+        layout
+    }
+
+    pub(crate) fn wrap(&self) -> Option<(usize, WrapMode)> {
+        self.wrap
+    }
+
+    /// Update rows of lines affected by edits described with `deltas`, the
+    /// way they are built from line changes
+    pub(crate) fn update(&mut self, rope: &Rope, deltas: &[DeltaType]) {
+        if self.wrap.is_none() {
+            return;
+        }
+        for delta in deltas {
+            match *delta {
+                DeltaType::LinesInserted { idx, count } => {
+                    self.breaks
+                        .splice(idx - 1..idx - 1, std::iter::repeat(vec![]).take(count));
+                }
+                DeltaType::LinesRemoved { idx, count } => {
+                    self.breaks.drain(idx - 1..idx - 1 + count);
+                }
+                DeltaType::LineChanged { idx, .. } => {
+                    self.breaks[idx - 1] = self.wrap_line(rope, idx - 1);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Get rows of the line `line`, which must exist
+    pub(crate) fn rows(&self, rope: &Rope, line: usize) -> Vec<VisualRow> {
+        let line_length = rope.line_length(line).expect("Line must exist");
+        let breaks = self.breaks.get(line - 1).map(Vec::as_slice).unwrap_or(&[]);
+        let starts = std::iter::once(1).chain(breaks.iter().copied());
+        let ends = breaks
+            .iter()
+            .copied()
+            .chain(std::iter::once(line_length + 1));
+        starts
+            .zip(ends)
+            .map(|(start, end)| VisualRow { line, start, end })
+            .collect()
+    }
+
+    /// Get the position `n` rows below `position`, or above it if `n` is
+    /// negative, keeping the display column `x` relative to the start of the
+    /// row, where `x` counts from the start of the line of `position`. The
+    /// position stays within the row unless `x` is `sticky`, then it is
+    /// restored on the following rows of a long enough line, like up/down
+    /// movements do. If the line is too short or the column falls inside a
+    /// wide grapheme cluster, the nearest column before is taken and the
+    /// missed display column, counting from the start of the new line, is
+    /// returned as well.
+    pub(crate) fn move_rows(
+        &self,
+        rope: &Rope,
+        position: PositionUnbound,
+        x: usize,
+        sticky: bool,
+        n: isize,
+    ) -> (PositionUnbound, Option<usize>) {
+        let mut line = position.line.get();
+        let mut rows = self.rows(rope, line);
+        let mut idx = row_idx(&rows, position.col.get());
+        let x = x.saturating_sub(display_col(rope, line - 1, rows[idx].start, self.tab_width));
+        for _ in 0..n.unsigned_abs() {
+            if n < 0 && idx > 0 {
+                idx -= 1;
+            } else if n < 0 && line > 1 {
+                line -= 1;
+                rows = self.rows(rope, line);
+                idx = rows.len() - 1;
+            } else if n > 0 && idx + 1 < rows.len() {
+                idx += 1;
+            } else if n > 0 && line < rope.lines_count() {
+                line += 1;
+                rows = self.rows(rope, line);
+                idx = 0;
+            } else {
+                break;
+            }
+        }
+        let row = rows[idx];
+        let line_x = display_col(rope, line - 1, row.start, self.tab_width).saturating_add(x);
+        let col = col_at_display_col(rope, line - 1, line_x, self.tab_width).max(row.start);
+        let col = if sticky { col } else { col.min(row.end - 1) };
+        let missed = if display_col(rope, line - 1, col, self.tab_width) == line_x {
+            None
+        } else {
+            Some(line_x)
+        };
+        let position = PositionUnbound {
+            line: line.into(),
+            col: col.into(),
+        };
+        (position, missed)
+    }
+
+    /// Get columns rows of the line start at, except the first row
+    fn wrap_line(&self, rope: &Rope, line_idx: usize) -> Vec<usize> {
+        let (width, mode) = match self.wrap {
+            Some((width, mode)) => (width.max(1), mode),
+            None => return vec![],
+        };
+        let line_end = line_content_end(rope, line_idx);
+        let mut char_idx = rope.line_to_char(line_idx);
+        let mut breaks = vec![];
+        let mut col = 1;
+        let mut x = 0;
+        let mut row_x = 0;
+        // Column and display column after the last whitespace of the row
+        let mut word_start = None;
+        while char_idx < line_end {
+            let next = next_grapheme_boundary(rope, char_idx);
+            let grapheme = Cow::from(rope.slice(char_idx..next));
+            let grapheme_x = grapheme_width(&grapheme, x, self.tab_width);
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+            // Whitespace is left hanging at the row's end on word wrap
+            let fits = |x, row_x| x + grapheme_x - row_x <= width || x == row_x;
+            while !(fits(x, row_x) || mode == WrapMode::Word && is_whitespace) {
+                match word_start.take() {
+                    Some((word_col, word_x)) if mode == WrapMode::Word => {
+                        breaks.push(word_col);
+                        row_x = word_x;
+                    }
+                    _ => {
+                        breaks.push(col);
+                        row_x = x;
+                    }
+                }
+            }
+            x += grapheme_x;
+            col += 1;
+            char_idx = next;
+            if is_whitespace {
+                word_start = Some((col, x));
+            }
+        }
+        breaks
+    }
+}
+
+/// Get an index of the row containing the column `col`
+fn row_idx(rows: &[VisualRow], col: usize) -> usize {
+    rows.iter()
+        .rposition(|row| row.start <= col)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(text: &str, width: usize, mode: WrapMode) -> Vec<Vec<usize>> {
+        let rope = Rope::from_str(text);
+        let layout = Layout::new(&rope, Some((width, mode)), 4);
+        (1..=rope.len_lines())
+            .map(|line| layout.rows(&rope, line).iter().map(|r| r.start).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_chars() {
+        assert_eq!(
+            starts("abcdefg\nabc\n世界世界", 3, WrapMode::Char),
+            vec![vec![1, 4, 7], vec![1], vec![1, 2, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(
+            starts("ab cd efgh\nabcdefg hi", 6, WrapMode::Word),
+            vec![vec![1, 7], vec![1, 7]]
+        );
+        assert_eq!(
+            starts("abcdefg hi\nab cd\nab  cd\nabcd efg", 4, WrapMode::Word),
+            vec![vec![1, 5, 9], vec![1, 4], vec![1, 5], vec![1, 6]]
+        );
+    }
+
+    #[test]
+    fn test_rows_end_with_line_break() {
+        let rope = Rope::from_str("abcd\nab");
+        let layout = Layout::new(&rope, Some((3, WrapMode::Char)), 4);
+        assert_eq!(
+            layout.rows(&rope, 1),
+            vec![
+                VisualRow {
+                    line: 1,
+                    start: 1,
+                    end: 4
+                },
+                VisualRow {
+                    line: 1,
+                    start: 4,
+                    end: 6
+                },
+            ]
+        );
+        let layout = Layout::new(&rope, None, 4);
+        assert_eq!(
+            layout.rows(&rope, 2),
+            vec![VisualRow {
+                line: 2,
+                start: 1,
+                end: 4
+            }]
+        );
+    }
+}
@@ -1,14 +1,30 @@
 //! Crate providing `Buffer`: core part of your text editor
 // #![deny(missing_docs)]
 mod buffer;
+mod changes;
+mod history;
+mod layout;
+mod registers;
 mod selections;
+mod text;
+mod text_object;
 mod util;
+mod viewport;
 pub use buffer::Buffer;
+pub use changes::{Assoc, ChangeSet, Operation};
+pub use layout::{VisualRow, WrapMode};
+pub use regex::Regex;
+pub use registers::{Registers, DEFAULT_REGISTER};
 pub use ropey::Rope;
+use ropey::RopeSlice;
 pub use selections::CursorDirection;
 pub use selections::{Position, Selection};
 use selections::{PositionUnbound, SelectionUnbound};
 use std::io;
+use std::path::PathBuf;
+pub use text::{AutoIndent, IndentUnit, LineEnding};
+pub use text_object::{ObjectScope, TextObject};
+pub use viewport::Viewport;
 
 /// Crate's error type
 #[derive(Debug, thiserror::Error)]
@@ -16,6 +32,27 @@ pub enum Error {
     /// Failure on buffer creation from `Reader`
     #[error("Unable to create buffer with reader: {0}")]
     CreateFromReader(#[from] io::Error),
+    /// There is no revision with such id in the undo tree
+    #[error("Revision {0} not found")]
+    RevisionNotFound(usize),
+    /// An operation would leave the buffer without selections
+    #[error("Nothing selected")]
+    NothingSelected,
+    /// There is no selection with such index
+    #[error("Selection {0} not found")]
+    SelectionNotFound(usize),
+    /// Failure on writing buffer's contents to `Writer`
+    #[error("Unable to write buffer: {0}")]
+    Write(#[source] io::Error),
+    /// Failure on saving buffer to a file
+    #[error("Unable to save buffer to {path}: {source}")]
+    Save {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
 }
 
 /// Result with crate's error type applied
@@ -30,12 +67,28 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 /// For selections tests usage of this trait makes a rope creation and
 /// filling unnecessary.
 pub trait LineLength {
-    /// Return the length of the line specified by `line`. Note the first
-    /// line has the index equal 1.
+    /// Return the length of the line specified by `line` in grapheme clusters
+    /// including one position for the line break. Note the first line has
+    /// the index equal 1.
     fn line_length(&self, line: usize) -> Option<usize>;
 
     /// Return the count of lines.
     fn lines_count(&self) -> usize;
+
+    /// Return the display column, starting from 0, where the grapheme cluster
+    /// at `col` of the line `line` starts. By default each grapheme cluster
+    /// takes one column.
+    fn display_col(&self, _line: usize, col: usize) -> usize {
+        col - 1
+    }
+
+    /// Return the column of the grapheme cluster of the line `line` which
+    /// covers the display column `x`, or the line break's column if the line
+    /// is shorter.
+    fn col_at_display_col(&self, line: usize, x: usize) -> Option<usize> {
+        self.line_length(line)
+            .map(|length| length.min(x.saturating_add(1)))
+    }
 }
 
 impl<T: LineLength + ?Sized> LineLength for &T {
@@ -46,10 +99,22 @@ impl<T: LineLength + ?Sized> LineLength for &T {
     fn lines_count(&self) -> usize {
         (*self).lines_count()
     }
+
+    fn display_col(&self, line: usize, col: usize) -> usize {
+        (*self).display_col(line, col)
+    }
+
+    fn col_at_display_col(&self, line: usize, x: usize) -> Option<usize> {
+        (*self).col_at_display_col(line, x)
+    }
 }
 
 /// Buffer's feedback for optimal redraws or any other case when full buffer
-/// contents not needed
+/// contents not needed.
+///
+/// Lines insertions and removals go in order they happened, each one uses
+/// line indices right after the previous one; indices of changed lines
+/// are given for the final state.
 #[derive(Debug, PartialEq)]
 pub struct Delta<'a, 'b> {
     pub buffer: &'a Buffer,
@@ -73,12 +138,31 @@ pub enum DeltaType<'a> {
         /// Deleted selection info
         identity: PositionUnbound,
     },
+    /// Another selection became the main one
+    MainSelectionChanged {
+        /// Identity of the new main selection
+        identity: PositionUnbound,
+    },
     /// Line's contents changed
     LineChanged {
         /// Line index
         idx: usize,
-        /// Line new content
-        content: &'a str,
+        /// Line new content including its line break
+        content: RopeSlice<'a>,
+    },
+    /// New lines inserted, so lines starting from `idx` are shifted down
+    LinesInserted {
+        /// Index of the first inserted line
+        idx: usize,
+        /// Count of inserted lines
+        count: usize,
+    },
+    /// Lines removed, so lines after them are shifted up
+    LinesRemoved {
+        /// Index of the first removed line
+        idx: usize,
+        /// Count of removed lines
+        count: usize,
     },
 }
 
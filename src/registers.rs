@@ -0,0 +1,53 @@
+//! Named registers to yank text into and paste it from, one value per
+//! selection
+use std::collections::HashMap;
+
+/// Name of the register used when no other one is specified
+pub const DEFAULT_REGISTER: char = '"';
+
+/// Storage of named registers. It is not tied to a buffer, so an embedder
+/// can share one between several buffers to copy text across them.
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    values: HashMap<char, Vec<String>>,
+}
+
+impl Registers {
+    /// Create registers with nothing stored
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get values stored in the register `name`, one per selection they
+    /// were yanked from
+    pub fn get(&self, name: char) -> Option<&[String]> {
+        self.values.get(&name).map(Vec::as_slice)
+    }
+
+    /// Store `values` in the register `name` replacing previous ones
+    pub fn set(&mut self, name: char, values: Vec<String>) {
+        self.values.insert(name, values);
+    }
+
+    /// Clear the register `name`
+    pub fn clear(&mut self, name: char) {
+        self.values.remove(&name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_clear() {
+        let mut registers = Registers::new();
+        assert_eq!(registers.get(DEFAULT_REGISTER), None);
+        registers.set('a', vec!["foo".to_owned(), "bar".to_owned()]);
+        registers.set('a', vec!["baz".to_owned()]);
+        assert_eq!(registers.get('a'), Some(&["baz".to_owned()][..]));
+        assert_eq!(registers.get(DEFAULT_REGISTER), None);
+        registers.clear('a');
+        assert_eq!(registers.get('a'), None);
+    }
+}
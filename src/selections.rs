@@ -1,7 +1,10 @@
 //! Code specific to individual selection
 pub(crate) mod storage;
+use crate::text::{line_content_end, next_grapheme_boundary};
 use crate::LineLength;
 use crate::{util::PositiveUsize, Buffer};
+use ropey::Rope;
+use std::ops::Range;
 #[cfg(test)]
 mod tests;
 
@@ -65,6 +68,12 @@ impl Position<'_> {
     pub fn is_line_end(&self) -> bool {
         self.position.is_line_end(self.buffer.get_rope())
     }
+
+    /// Returns an index of the first char of the grapheme cluster
+    /// at the position in the underlying rope
+    pub fn char_idx(&self) -> usize {
+        self.position.to_char_idx(self.buffer.get_rope())
+    }
 }
 
 /// A position in a text buffer represented by 1-based numbered
@@ -135,6 +144,39 @@ impl PositionUnbound {
             .map(|x| self.col.get() >= x)
             .unwrap_or(false)
     }
+
+    /// Get an index of the first char of the grapheme cluster at the position.
+    /// Columns past the line end are clamped to it.
+    pub(crate) fn to_char_idx(self, rope: &Rope) -> usize {
+        let line_idx = self.line.get() - 1;
+        let line_end = line_content_end(rope, line_idx);
+        let mut char_idx = rope.line_to_char(line_idx);
+        for _ in 1..self.col.get() {
+            if char_idx >= line_end {
+                break;
+            }
+            char_idx = next_grapheme_boundary(rope, char_idx);
+        }
+        char_idx
+    }
+
+    /// Build a position of the grapheme cluster which contains the char
+    /// with index `char_idx`
+    pub(crate) fn from_char_idx(rope: &Rope, char_idx: usize) -> Self {
+        let line_idx = rope.char_to_line(char_idx);
+        let mut boundary = rope.line_to_char(line_idx);
+        let mut col = 1;
+        while boundary < char_idx {
+            boundary = next_grapheme_boundary(rope, boundary);
+            if boundary <= char_idx {
+                col += 1;
+            }
+        }
+        PositionUnbound {
+            line: (line_idx + 1).into(),
+            col: col.into(),
+        }
+    }
 }
 
 /// For selection the head must be less than the tail, but
@@ -171,6 +213,8 @@ pub struct Selection<'a> {
     pub(crate) selection: SelectionUnbound,
     /// Link to the buffer
     pub(crate) buffer: &'a Buffer,
+    /// Whether the selection is the buffer's main one
+    pub(crate) is_main: bool,
 }
 
 impl PartialEq for Selection<'_> {
@@ -206,6 +250,11 @@ impl<'a> Selection<'a> {
     pub fn bounds(&self) -> (Position<'a>, Position<'a>) {
         (self.from(), self.to())
     }
+
+    /// Check if the selection is the buffer's main one
+    pub fn is_main(&self) -> bool {
+        self.is_main
+    }
 }
 
 /// Selection is as pair of positions, which are pairs of line/column values with
@@ -224,16 +273,46 @@ pub struct SelectionUnbound {
     /// remembering its previous column as a "sticky column". If a subsequent
     /// up/down movement leads to a line longer than this value the sticky column
     /// will restore the selection's original column. Left/right movements will
-    /// reset `sticky_column`.
+    /// reset `sticky_column`. It counts display columns from the line start
+    /// starting from 1, so tabs and wide chars are taken into account.
     pub(crate) sticky_column: Option<PositiveUsize>,
 }
 
 impl SelectionUnbound {
+    /// Build a selection of grapheme clusters covering non-empty chars
+    /// `range`; a selection of length 1 is always forward
+    pub(crate) fn from_char_range(
+        rope: &Rope,
+        range: Range<usize>,
+        cursor_direction: CursorDirection,
+    ) -> Self {
+        let mut selection = SelectionUnbound {
+            from: PositionUnbound::from_char_idx(rope, range.start),
+            to: PositionUnbound::from_char_idx(rope, range.end - 1),
+            cursor_direction,
+            sticky_column: None,
+        };
+        if selection.is_point() {
+            selection.cursor_direction = CursorDirection::Forward;
+        }
+        selection
+    }
+
+    /// Get chars range the selection covers. Selection is inclusive so
+    /// the whole grapheme cluster under `to` is included too.
+    pub(crate) fn to_char_range(&self, rope: &Rope) -> Range<usize> {
+        let (from, to) = self.get_bounds();
+        let from = from.to_char_idx(rope);
+        let to = next_grapheme_boundary(rope, to.to_char_idx(rope));
+        from..to.max(from)
+    }
+
     /// Build a binded selection
-    pub(crate) fn binded(self, buffer: &Buffer) -> Selection {
+    pub(crate) fn binded(self, buffer: &Buffer, is_main: bool) -> Selection {
         Selection {
             selection: self,
             buffer,
+            is_main,
         }
     }
 
@@ -309,39 +388,22 @@ impl SelectionUnbound {
         }
     }
 
-    /// Move selection right keeping its shape if possible.
-    /// If it is a multiline selection then only head is affected.
-    ///
-    /// This is what happens on characters deletion before the selection.
-    pub(crate) fn nudge_left(&mut self, n: usize) {
-        self.from.col.sub_assign(n);
-        if self.to.line == self.from.line {
-            self.to.col.sub_assign(n);
-        }
-    }
+    // Actions triggered by user directly (meaning "move_x" command, not a helper methods):
 
-    /// Move selection right keeping its shape if possible.
-    /// If it is a multiline selection then only head is affected.
-    ///
-    /// This is what happens on characters insertion before the selection.
-    pub(crate) fn nudge_right(&mut self, n: usize) {
-        self.from.col.add_assign(n);
-        if self.to.line == self.from.line {
-            self.to.col.add_assign(n);
+    /// Move cursor to the position, shrinking selection to length 1 if
+    /// not `extend`
+    pub(crate) fn move_to(mut self, position: PositionUnbound, extend: bool) -> Self {
+        *self.get_cursor_mut() = position;
+        if !extend {
+            self.from = position;
+            self.to = position;
+            self.cursor_direction = CursorDirection::Forward;
         }
+        self.fix_direction();
+        self.drop_sticky();
+        self
     }
 
-    /// Move selection up keeping its shape.
-    /// No checks are made and lengths used as it is a helper method.
-    ///
-    /// This is what happens on newlines deletion above.
-    pub(crate) fn nudge_up(&mut self, n: usize) {
-        self.from.line.sub_assign(n);
-        self.to.line.sub_assign(n);
-    }
-
-    // Actions triggered by user directly (meaning "move_x" command, not a helper methods):
-
     /// Move cursor left by n characters, handling line lengthes and buffer bounds
     pub(crate) fn move_left<L: LineLength>(
         mut self,
@@ -409,29 +471,41 @@ impl SelectionUnbound {
         self
     }
 
+    /// Get the display column up/down movements keep the cursor at: the sticky
+    /// one if any or the column the cursor is displayed at
+    fn cursor_display_col<L: LineLength>(&self, line_length: &L) -> usize {
+        match self.sticky_column {
+            Some(sticky_column) => sticky_column.get() - 1,
+            None => {
+                let cursor = self.get_cursor();
+                line_length.display_col(cursor.line.get(), cursor.col.get())
+            }
+        }
+    }
+
+    /// Place the cursor, which got onto another line, at the grapheme cluster
+    /// covering the display column `x`. Unless it starts exactly there, `x`
+    /// is remembered as the sticky column.
+    fn place_at_display_col<L: LineLength>(&mut self, x: usize, line_length: &L) {
+        let line = self.get_cursor().line.get();
+        if let Some(col) = line_length.col_at_display_col(line, x) {
+            self.get_cursor_mut().col = col.into();
+            self.sticky_column = if line_length.display_col(line, col) == x {
+                None
+            } else {
+                Some(x.saturating_add(1).into())
+            };
+        }
+    }
+
     /// Move cursor up by n lines, handling line lengthes and buffer bounds;
     /// If line is shorter, then previous column is preserved as sticky column
     /// and will be restored on enough lenth.
     pub(crate) fn move_up<L: LineLength>(mut self, n: usize, extend: bool, line_length: L) -> Self {
-        let current_sticky_column = self.sticky_column;
+        let x = self.cursor_display_col(&line_length);
         let cursor = self.get_cursor_mut();
         cursor.line.sub_assign(n);
-        if let Some(line_length) = line_length.line_length(cursor.line.get()) {
-            if let Some(sticky_column) = current_sticky_column {
-                if sticky_column.get() < line_length {
-                    cursor.col = sticky_column;
-                    self.sticky_column = None;
-                } else {
-                    cursor.col = line_length.into();
-                }
-            } else {
-                if line_length < cursor.col.get() {
-                    let sticky_column = Some(cursor.col);
-                    cursor.col = line_length.into();
-                    self.sticky_column = sticky_column;
-                }
-            }
-        }
+        self.place_at_display_col(x, &line_length);
         self.fix_direction();
         if !extend {
             self.drop_selection();
@@ -439,6 +513,41 @@ impl SelectionUnbound {
         self
     }
 
+    /// Move cursor to the end of its line, that is the position of the line
+    /// break. The selection sticks to line ends on subsequent up/down
+    /// movements.
+    pub(crate) fn move_to_line_end<L: LineLength>(self, extend: bool, line_length: L) -> Self {
+        let line = self.get_cursor().line;
+        let col = line_length
+            .line_length(line.get())
+            .expect("Selection reached inconsistency");
+        let mut selection = self.move_to(
+            PositionUnbound {
+                line,
+                col: col.into(),
+            },
+            extend,
+        );
+        selection.sticky_column = Some(usize::MAX.into());
+        selection
+    }
+
+    /// Move cursor to the line `line` keeping its column the same way as
+    /// up/down movements do
+    pub(crate) fn move_to_line<L: LineLength>(
+        self,
+        line: usize,
+        extend: bool,
+        line_length: L,
+    ) -> Self {
+        let current = self.get_cursor().line.get();
+        if line < current {
+            self.move_up(current - line, extend, line_length)
+        } else {
+            self.move_down(line - current, extend, line_length)
+        }
+    }
+
     /// Move cursor down by n lines, handling line lengthes and buffer bounds;
     /// If line is shorter, then previous column is preserved as sticky column
     /// and will be restored on enough lenth.
@@ -448,7 +557,7 @@ impl SelectionUnbound {
         extend: bool,
         line_length: L,
     ) -> Self {
-        let current_sticky_column = self.sticky_column;
+        let x = self.cursor_display_col(&line_length);
         let cursor = self.get_cursor_mut();
         let target: usize = cursor.line.get() + n;
         let lines_count = line_length.lines_count();
@@ -457,22 +566,7 @@ impl SelectionUnbound {
         } else {
             cursor.line.add_assign(n);
         }
-        if let Some(line_length) = line_length.line_length(cursor.line.get()) {
-            if let Some(sticky_column) = current_sticky_column {
-                if sticky_column.get() < line_length {
-                    cursor.col = sticky_column;
-                    self.sticky_column = None;
-                } else {
-                    cursor.col = line_length.into();
-                }
-            } else {
-                if line_length < cursor.col.get() {
-                    let sticky_column = Some(cursor.col);
-                    cursor.col = line_length.into();
-                    self.sticky_column = sticky_column;
-                }
-            }
-        }
+        self.place_at_display_col(x, &line_length);
         self.fix_direction();
         if !extend {
             self.drop_selection();
@@ -542,26 +636,11 @@ impl SelectionUnbound {
     /// is an assignment of a cursor to an existing position
     #[cfg(test)]
     pub(crate) fn set(&mut self, line: usize, col: usize, extend: bool) {
-        match self.cursor_direction {
-            CursorDirection::Forward => {
-                self.to.line = line.into();
-                self.to.col = col.into();
-                if !extend {
-                    self.from = self.to;
-                    self.cursor_direction = CursorDirection::Forward;
-                }
-            }
-            CursorDirection::Backward => {
-                self.from.line = line.into();
-                self.from.col = col.into();
-                if !extend {
-                    self.to = self.from;
-                    self.cursor_direction = CursorDirection::Forward;
-                }
-            }
-        }
-        self.fix_direction();
-        self.drop_sticky();
+        let position = PositionUnbound {
+            line: line.into(),
+            col: col.into(),
+        };
+        *self = self.clone().move_to(position, extend);
     }
 }
 
@@ -1,18 +1,23 @@
 //! Selections storage API with an implementation respecting multiple selections
 //! interaction.
-use super::{CursorDirection, PositionUnbound, SelectionUnbound};
+#[cfg(test)]
+use super::CursorDirection;
+use super::{PositionUnbound, SelectionUnbound};
+use crate::changes::{Assoc, ChangeSet};
 use crate::{DeltaType, LineLength};
 #[cfg(test)]
 mod tests;
 
-use itertools::Itertools;
+use itertools::{EitherOrBoth, Itertools};
+use ropey::Rope;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::ops::Bound;
 
 /// As selections within the buffer are not independent
 /// (can be merged, for instance) this structure is aimed
 /// to take special care of it
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct SelectionStorage {
     // TODO: while the Tree is ok to store selections,
     // Rust std implementation's API is restrictive;
@@ -42,6 +47,8 @@ pub(crate) struct SelectionStorage {
     ///    it sounds as it might be better to mark `main` the last one
     ///    within parts created from a bigger `main` not overall last,
     ///    but it is how Kakoune does)
+    /// 4. if `main` is removed, the nearest selection before it becomes
+    ///    `main` or the first one if there is no such selection
     main_selection_ptr: PositionUnbound,
 }
 
@@ -62,11 +69,32 @@ impl SelectionStorage {
     /// Add a selection to the storage.
     /// If storage contains a selection which overlaps with the input
     /// they will be merged. This check is run twice: for head and for
-    /// tail.
+    /// tail; selections lying entirely inside the input are dropped.
     pub(crate) fn add_selection<'a, 'b: 'a>(
         &'a mut self,
-        mut ns: SelectionUnbound,
+        ns: SelectionUnbound,
     ) -> Vec<DeltaType<'b>> {
+        let head: SelectionIntersect = SelectionUnbound::from(ns.from).into();
+        let covered: Vec<SelectionIntersect> = self
+            .selections_tree
+            .range((Bound::Excluded(head), Bound::Unbounded))
+            .take_while(|s| s.0.to < ns.to)
+            .cloned()
+            .collect();
+        let mut deltas = Vec::with_capacity(covered.len());
+        for s in covered {
+            self.selections_tree.remove(&s);
+            if self.main_selection_ptr == s.0.from {
+                self.main_selection_ptr = ns.from;
+            }
+            deltas.push(DeltaType::SelectionDeleted { identity: s.0.from });
+        }
+        deltas.extend(self.merge_selection(ns));
+        deltas
+    }
+
+    /// Add a selection merging it with ones hit by its ends
+    fn merge_selection<'a, 'b: 'a>(&'a mut self, mut ns: SelectionUnbound) -> Vec<DeltaType<'b>> {
         match (self.find_hit_take(ns.from), self.find_hit_take(ns.to)) {
             (None, None) => {
                 // No intersections
@@ -78,7 +106,7 @@ impl SelectionStorage {
                 if self.main_selection_ptr == ns.from {
                     self.main_selection_ptr = s.from;
                 }
-                s.to = ns.to;
+                s.to = s.to.max(ns.to);
                 self.add_selection(s.clone());
                 vec![
                     DeltaType::SelectionChanged {
@@ -156,25 +184,217 @@ impl SelectionStorage {
             .map(|si| si.0)
     }
 
+    /// Finds a selection which covers input position.
+    pub(crate) fn find_hit(&self, s: PositionUnbound) -> Option<&SelectionUnbound> {
+        self.selections_tree
+            .get(&SelectionUnbound::from(s).into())
+            .map(|si| &si.0)
+    }
+
     /// Apply functions to each of selections making a new tree in place of the old one.
-    fn apply_to_selections<'a, 'b: 'a, F>(&'a mut self, f: F) -> Vec<DeltaType<'b>>
+    pub(crate) fn apply_to_selections<'a, 'b: 'a, F>(&'a mut self, f: F) -> Vec<DeltaType<'b>>
     where
         F: Fn(SelectionUnbound) -> SelectionUnbound,
     {
+        let main_index = self.main_index();
         let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
         let mut unbound_deltas = Vec::with_capacity(selections_old.len());
-        for s in selections_old {
+        let mut main = self.main_selection_ptr;
+        for (idx, s) in selections_old.into_iter().enumerate() {
             let new = f(s.0);
+            if idx == main_index {
+                main = new.from;
+            }
             unbound_deltas.extend(self.add_selection(new));
         }
+        self.point_main_at(main);
         unbound_deltas
     }
 
+    /// Make the selection containing `position` the main one, which is
+    /// where the main selection ended up after its rebuild
+    fn point_main_at(&mut self, position: PositionUnbound) {
+        self.main_selection_ptr = self.find_hit(position).map_or(position, |s| s.from);
+    }
+
+    /// Replace all selections with ones from another storage, for instance,
+    /// a previously saved state.
+    pub(crate) fn restore<'a, 'b: 'a>(&'a mut self, other: SelectionStorage) -> Vec<DeltaType<'b>> {
+        let previous = std::mem::replace(self, other);
+        self.diff(&previous)
+    }
+
+    /// Describe how to get to the current state from `previous`: selections
+    /// which are absent now are deleted and new ones are added, then the
+    /// main selection is marked if it took another place among selections.
+    pub(crate) fn diff<'a, 'b: 'a>(&'a self, previous: &SelectionStorage) -> Vec<DeltaType<'b>> {
+        let mut deleted = vec![];
+        let mut added = vec![];
+        for pair in previous
+            .iter()
+            .merge_join_by(self.iter(), |old, new| old.from.cmp(&new.from))
+        {
+            match pair {
+                EitherOrBoth::Both(old, new) if old != new => {
+                    deleted.push(DeltaType::SelectionDeleted { identity: old.from });
+                    added.push(DeltaType::SelectionAdded { selection: new });
+                }
+                EitherOrBoth::Both(..) => {}
+                EitherOrBoth::Left(old) => {
+                    deleted.push(DeltaType::SelectionDeleted { identity: old.from })
+                }
+                EitherOrBoth::Right(new) => {
+                    added.push(DeltaType::SelectionAdded { selection: new })
+                }
+            }
+        }
+        deleted.extend(added);
+        if previous.main_index() != self.main_index() {
+            deleted.push(DeltaType::MainSelectionChanged {
+                identity: self.main_selection_ptr,
+            });
+        }
+        deleted
+    }
+
+    /// Replace all selections with new ones merging overlapping ones.
+    /// The last of new selections becomes the main one.
+    pub(crate) fn replace_all<'a, 'b: 'a>(
+        &'a mut self,
+        selections: Vec<SelectionUnbound>,
+    ) -> Vec<DeltaType<'b>> {
+        let main = selections.len().saturating_sub(1);
+        self.replace_with_main(selections, main)
+    }
+
+    /// Replace all selections with new ones merging overlapping ones.
+    /// The new selection with index `main` becomes the main one.
+    pub(crate) fn replace_with_main<'a, 'b: 'a>(
+        &'a mut self,
+        selections: Vec<SelectionUnbound>,
+        main: usize,
+    ) -> Vec<DeltaType<'b>> {
+        let previous = self.clone();
+        self.selections_tree.clear();
+        let main = selections.get(main).map(|s| s.from);
+        for s in selections {
+            self.add_selection(s);
+        }
+        if let Some(main) = main.and_then(|from| self.find_hit(from)) {
+            self.main_selection_ptr = main.from;
+        }
+        self.diff(&previous)
+    }
+
+    /// Remove selections which don't satisfy the predicate, following
+    /// the rule of `main` selection removal. If no selection satisfies
+    /// the predicate there would be nothing left, so nothing is removed.
+    pub(crate) fn retain<'a, 'b: 'a, F>(&'a mut self, mut f: F) -> Vec<DeltaType<'b>>
+    where
+        F: FnMut(&SelectionUnbound) -> bool,
+    {
+        let (kept, removed): (BTreeSet<SelectionIntersect>, BTreeSet<SelectionIntersect>) =
+            std::mem::take(&mut self.selections_tree)
+                .into_iter()
+                .partition(|s| f(&s.0));
+        if kept.is_empty() {
+            self.selections_tree = removed;
+            return vec![];
+        }
+        self.selections_tree = kept;
+        let mut deltas: Vec<DeltaType> = removed
+            .into_iter()
+            .map(|s| DeltaType::SelectionDeleted { identity: s.0.from })
+            .collect();
+        if self.find_hit(self.main_selection_ptr).is_none() {
+            let main = self
+                .iter()
+                .rev()
+                .find(|s| s.from < self.main_selection_ptr)
+                .or_else(|| self.iter().next())
+                .expect("At least one selection is kept");
+            self.main_selection_ptr = main.from;
+            deltas.push(DeltaType::MainSelectionChanged {
+                identity: main.from,
+            });
+        }
+        deltas
+    }
+
+    /// Get the main selection
+    pub(crate) fn main_selection(&self) -> Option<&SelectionUnbound> {
+        self.selections_tree
+            .iter()
+            .nth(self.main_index())
+            .map(|s| &s.0)
+    }
+
+    /// Get an index of the main selection among all ones, that is the last
+    /// one starting not after the main selection pointer
+    pub(crate) fn main_index(&self) -> usize {
+        self.selections_tree
+            .iter()
+            .rposition(|s| s.0.from <= self.main_selection_ptr)
+            .unwrap_or(0)
+    }
+
+    /// Make the selection with `index` the main one; returns `None` if
+    /// there is no such selection.
+    pub(crate) fn set_main<'a, 'b: 'a>(&'a mut self, index: usize) -> Option<Vec<DeltaType<'b>>> {
+        let main = self.iter().nth(index)?;
+        if main.from == self.main_selection_ptr {
+            return Some(vec![]);
+        }
+        self.main_selection_ptr = main.from;
+        Some(vec![DeltaType::MainSelectionChanged {
+            identity: main.from,
+        }])
+    }
+
+    /// Drop all selections except the main one.
+    pub(crate) fn keep_only_main<'a, 'b: 'a>(&'a mut self) -> Vec<DeltaType<'b>> {
+        let main = self.main_selection().expect("Storage is never empty").from;
+        self.main_selection_ptr = main;
+        self.retain(|s| s.from == main)
+    }
+
+    /// Drop the main selection following the rule of `main` selection
+    /// removal; nothing is removed if it's the only selection.
+    pub(crate) fn remove_main<'a, 'b: 'a>(&'a mut self) -> Vec<DeltaType<'b>> {
+        let main = self.main_selection().expect("Storage is never empty").from;
+        self.main_selection_ptr = main;
+        self.retain(|s| s.from != main)
+    }
+
+    /// Make the selection `n` positions after the main one the new main,
+    /// or before it if `n` is negative, wrapping around.
+    pub(crate) fn rotate_main<'a, 'b: 'a>(&'a mut self, n: isize) -> Vec<DeltaType<'b>> {
+        let len = self.selections_tree.len() as isize;
+        let index = (self.main_index() as isize + n).rem_euclid(len);
+        self.set_main(index as usize)
+            .expect("Index is within selections count")
+    }
+
     /// Swap selections' cursor.
     pub(crate) fn swap_cursor<'a, 'b: 'a>(&'a mut self) -> Vec<DeltaType<'b>> {
         self.apply_to_selections(move |s| s.swap_cursor())
     }
 
+    /// Move cursors of all selections to positions found from current ones.
+    pub(crate) fn move_cursors<'a, 'b: 'a, F>(
+        &'a mut self,
+        extend: bool,
+        f: F,
+    ) -> Vec<DeltaType<'b>>
+    where
+        F: Fn(PositionUnbound) -> PositionUnbound,
+    {
+        self.apply_to_selections(move |s| {
+            let cursor = *s.get_cursor();
+            s.move_to(f(cursor), extend)
+        })
+    }
+
     /// Move left all selections.
     pub(crate) fn move_left<'a, 'b: 'a>(
         &'a mut self,
@@ -220,96 +440,12 @@ impl SelectionStorage {
         self.selections_tree.iter().map(|x| x.0.clone())
     }
 
-    /// Nudge all selections on `line` after specific column left.
-    pub(crate) fn move_left_on_line(&mut self, line: usize, after: usize, n: usize) {
-        let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
-
-        let (on_the_line, others): (Vec<SelectionUnbound>, Vec<SelectionUnbound>) = selections_old
-            .into_iter()
-            .map(|x| x.0)
-            .partition(|x| x.from.line == line.into() && x.from.col > after.into());
-        for mut s in on_the_line {
-            s.nudge_left(n);
-            self.add_selection(s);
-        }
-        for s in others {
-            self.add_selection(s);
-        }
-    }
-
-    /// Get a left neighbour for the selection.
-    /// It will be `None` if called for the first selection in the buffer.
-    pub(crate) fn get_first_before(&self, before: &SelectionUnbound) -> Option<SelectionUnbound> {
-        self.iter().rev().find(|s| s.to < before.from)
-    }
-
-    // /// Get a right neighbour for the selection.
-    // /// It will be `None` if called for the last selection in the buffer.
-    // pub(crate) fn get_first_after(&self, after: &SelectionRaw) -> Option<SelectionRaw> {
-    //     self.iter().find(|s| s.from > after.to)
-    // }
-
-    /// Compute selection storage after the selection deletion.
-    pub(crate) fn apply_delete<L: LineLength>(
-        &mut self,
-        mut to_delete: SelectionUnbound,
-        line_length: L,
-    ) {
-        let (from, to) = to_delete.get_bounds();
-        to_delete.drop_selection_to_head();
-        let to_line: usize = to.line.into();
-        let to_col: usize = to.col.into();
-        let from_col: usize = from.col.into();
-        let from_line: usize = from.line.into();
-        let chars_delta: usize = if to_line == from_line {
-            to_col - from_col + 1
-        } else {
-            to_col
-        };
-
-        to_delete.drop_selection_to_head();
-        self.replace_selection(to_delete.clone());
-
-        // As all API operations are atomic there must be no inconsistency between
-        // selections and text after each operation
-        let lines_delta = to_line - from_line
-            + if to_col
-                == line_length
-                    .line_length(to_line)
-                    .expect("Selection reached inconsistency")
-            {
-                1
-            } else {
-                0
-            };
-
-        self.move_left_on_line(to_line, to_col, chars_delta);
-
-        if lines_delta > 0 {
-            let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
-
-            let (selections_after, others): (Vec<SelectionUnbound>, Vec<SelectionUnbound>) =
-                selections_old
-                    .into_iter()
-                    .map(|x| x.0)
-                    .partition(|x| x.from > to_delete.to);
-
-            let mut selections_after_iter = selections_after.into_iter();
-            if let Some(mut first_after) = selections_after_iter.next() {
-                first_after.nudge_up(lines_delta);
-                first_after.nudge_right(from_col - 1);
-                self.add_selection(first_after);
-            }
-
-            for mut s in selections_after_iter {
-                s.nudge_up(lines_delta);
-                self.add_selection(s);
-            }
-
-            for s in others {
-                self.add_selection(s);
-            }
-        }
+    /// Shrink all selections to their heads.
+    pub(crate) fn drop_selections_to_head(&mut self) {
+        self.apply_to_selections(|mut s| {
+            s.drop_selection_to_head();
+            s
+        });
     }
 
     /// Place a new selection under each existing one with the same columns if it will fit the line.
@@ -320,88 +456,49 @@ impl SelectionStorage {
     ) -> Vec<DeltaType<'b>> {
         let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
         let mut unbound_deltas = Vec::with_capacity(selections_old.len());
+        let mut main_changed = false;
         for s in selections_old.into_iter().map(|x| x.0) {
             if let Some(selection_under) = s.create_selection_under(line_length.clone()) {
                 if self.main_selection_ptr == s.from {
                     self.main_selection_ptr = selection_under.from;
-                    // TODO: Delta for changed main selection
+                    main_changed = true;
                 }
                 unbound_deltas.extend(self.add_selection(selection_under));
             }
             self.add_selection(s);
         }
-        unbound_deltas
-    }
-
-    /// Find a selection that overlaps with the input selection and replace it.
-    /// Used to shrink the selection to be cursor-sized.
-    pub(crate) fn replace_selection(&mut self, to: SelectionUnbound) {
-        // TODO: perhaps we need some check here to verify that a replaced
-        // selection won't overlap the next one
-        self.selections_tree.replace(SelectionIntersect(to));
-    }
-
-    /// Move selections right, accumulating movement from a previous selection,
-    /// independently for each line.
-    /// For instance, if `n = 3`, then first selection will be moved right by 3,
-    /// next -- by 6, then by 9 and so on; other lines start with 3 too.
-    pub(crate) fn move_right_incremental(&mut self, n: usize) {
-        let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
-
-        let line_grouped = selections_old
-            .into_iter()
-            .map(|x| x.0)
-            .group_by(|x| x.from.line);
-        for (_, group) in &line_grouped {
-            let mut offset = n;
-            for mut s in group {
-                if (s.cursor_direction == CursorDirection::Backward) || s.is_point() {
-                    s.from.col.add_assign(offset);
-                }
-                s.to.col.add_assign(offset);
-                offset += n;
-                self.add_selection(s);
-            }
+        if main_changed {
+            unbound_deltas.push(DeltaType::MainSelectionChanged {
+                identity: self.main_selection_ptr,
+            });
         }
+        unbound_deltas
     }
 
-    /// Move selections down, accumulating movement from a previous selection.
-    /// For instance, if `n = 3`, then first selection will be moved down by 3,
-    /// next -- by 6, then by 9 and so on.
-    pub(crate) fn move_down_incremental(&mut self, n: usize) {
-        let selections_old = std::mem::replace(&mut self.selections_tree, BTreeSet::new());
-        let mut offset = n;
-        for mut s in selections_old.into_iter().map(|x| x.0) {
-            if s.is_point() {
-                s.from.line.add_assign(offset);
-                s.from.col = 1.into();
-                s.to.line.add_assign(offset);
-                s.to.col = 1.into();
-            } else if s.cursor_direction == CursorDirection::Backward {
-                let col_diff = s.to.col - s.from.col + 1.into();
-                s.from.line.add_assign(offset);
-                s.from.col = 1.into();
-                s.to.line.add_assign(offset);
-                s.to.col = col_diff;
-            } else {
-                s.from.line.add_assign(offset - n);
-                s.to.line.add_assign(offset);
-                s.to.col = 1.into();
+    /// Move selections along with the text they are on when the text is
+    /// changed, merging ones which start to overlap.
+    pub(crate) fn map_through(
+        &mut self,
+        changes: &ChangeSet,
+        assoc: Assoc,
+        rope_before: &Rope,
+        rope_after: &Rope,
+    ) {
+        let main_index = self.main_index();
+        let selections_old = std::mem::take(&mut self.selections_tree);
+        let mut main = self.main_selection_ptr;
+        for (idx, s) in selections_old.into_iter().map(|x| x.0).enumerate() {
+            let mapped = changes.map_selection(&s, assoc, rope_before, rope_after);
+            if idx == main_index {
+                main = mapped.from;
             }
-            offset += n;
-            self.add_selection(s);
+            self.add_selection(mapped);
         }
+        self.point_main_at(main);
     }
 
     // Test related stuff:
 
-    #[cfg(test)]
-    fn find_hit(&self, s: PositionUnbound) -> Option<&SelectionUnbound> {
-        self.selections_tree
-            .get(&SelectionUnbound::from(s).into())
-            .map(|si| &si.0)
-    }
-
     #[cfg(test)]
     pub(crate) fn gen_from_tuples(selections: &[SelectionQuick]) -> Self {
         let mut storage = SelectionStorage::new();
@@ -446,7 +543,7 @@ impl From<SelectionIntersect> for SelectionUnbound {
 /// violates transitivity. Thus `SelectionIntersect` is a hack to tweak
 /// `BTreeSet` search and will be valid only within storage's `add_selection`
 /// implementation, which handles this case manually.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct SelectionIntersect(pub(crate) SelectionUnbound);
 
 impl Eq for SelectionIntersect {}
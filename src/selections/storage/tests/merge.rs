@@ -156,3 +156,44 @@ fn test_merge_both() {
 
     assert_eq!(selections_vec, selections_reference_vec);
 }
+
+#[test]
+fn test_merge_inner() {
+    let mut storage = gen_storage();
+    let s = SelectionUnbound::new_quick(2, 15, 2, 20, Default::default());
+    storage.add_selection(s);
+
+    // Unwrapped from newtype to provide intuitive comparison
+    let selections_vec: Vec<SelectionUnbound> = storage
+        .selections_tree
+        .into_iter()
+        .map(|x| x.into())
+        .collect();
+    let selections_reference_vec = vec![
+        SelectionUnbound::new_quick(1, 10, 1, 30, Default::default()),
+        SelectionUnbound::new_quick(2, 10, 2, 30, Default::default()),
+        SelectionUnbound::new_quick(3, 10, 5, 130, Default::default()),
+    ];
+
+    assert_eq!(selections_vec, selections_reference_vec);
+}
+
+#[test]
+fn test_merge_covered() {
+    let mut storage = gen_storage();
+    let s = SelectionUnbound::new_quick(1, 5, 2, 35, Default::default());
+    storage.add_selection(s);
+
+    // Unwrapped from newtype to provide intuitive comparison
+    let selections_vec: Vec<SelectionUnbound> = storage
+        .selections_tree
+        .into_iter()
+        .map(|x| x.into())
+        .collect();
+    let selections_reference_vec = vec![
+        SelectionUnbound::new_quick(1, 5, 2, 35, Default::default()),
+        SelectionUnbound::new_quick(3, 10, 5, 130, Default::default()),
+    ];
+
+    assert_eq!(selections_vec, selections_reference_vec);
+}
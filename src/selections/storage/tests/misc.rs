@@ -1,4 +1,5 @@
 use super::super::*;
+use super::gen_storage;
 use pretty_assertions::assert_eq;
 use std::collections::HashMap;
 
@@ -64,3 +65,50 @@ fn test_main_selection_merge() {
         }
     );
 }
+
+#[test]
+fn test_main_selection_removed() {
+    let mut storage = gen_storage();
+    storage.main_selection_ptr = PositionUnbound {
+        line: 2.into(),
+        col: 10.into(),
+    };
+
+    let deltas = storage.retain(|s| s.from.line != 2.into());
+    assert_eq!(
+        deltas,
+        vec![
+            DeltaType::SelectionDeleted {
+                identity: PositionUnbound {
+                    line: 2.into(),
+                    col: 10.into()
+                }
+            },
+            DeltaType::MainSelectionChanged {
+                identity: PositionUnbound {
+                    line: 1.into(),
+                    col: 10.into()
+                }
+            }
+        ]
+    );
+    assert_eq!(
+        storage.main_selection_ptr,
+        PositionUnbound {
+            line: 1.into(),
+            col: 10.into()
+        }
+    );
+
+    storage.retain(|s| s.from.line == 3.into());
+    assert_eq!(
+        storage.main_selection_ptr,
+        PositionUnbound {
+            line: 3.into(),
+            col: 10.into()
+        }
+    );
+
+    assert!(storage.retain(|_| false).is_empty());
+    assert_eq!(storage.iter().count(), 1);
+}
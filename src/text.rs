@@ -0,0 +1,809 @@
+//! Navigation over `Rope` text: columns count extended grapheme clusters,
+//! user-perceived characters, rather than chars
+use regex::Regex;
+use ropey::{str_utils::byte_to_char_idx, Rope};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+use unicode_width::UnicodeWidthStr;
+
+/// Byte order mark which could start a text
+pub(crate) const BOM: char = '\u{FEFF}';
+
+/// Line break sequences a buffer could use
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LineEnding {
+    /// `\n`, common for Unix-like systems
+    #[default]
+    Lf,
+    /// `\r\n`, common for Windows
+    CrLf,
+    /// `\r`, used by classic Mac OS
+    Cr,
+    /// Next line, `U+0085`
+    Nel,
+    /// Line separator, `U+2028`
+    Ls,
+    /// Paragraph separator, `U+2029`
+    Ps,
+}
+
+impl LineEnding {
+    const ALL: [LineEnding; 6] = [
+        LineEnding::Lf,
+        LineEnding::CrLf,
+        LineEnding::Cr,
+        LineEnding::Nel,
+        LineEnding::Ls,
+        LineEnding::Ps,
+    ];
+
+    /// Get the line break sequence
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+            LineEnding::Nel => "\u{0085}",
+            LineEnding::Ls => "\u{2028}",
+            LineEnding::Ps => "\u{2029}",
+        }
+    }
+
+    /// Recognize a line break sequence. Vertical tab and form feed
+    /// break lines too, but are not considered line endings.
+    pub(crate) fn from_line_break(line_break: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|ending| ending.as_str() == line_break)
+            .copied()
+    }
+
+    /// Find the most used line ending in the text; if there are no line
+    /// breaks the default one is used.
+    pub(crate) fn detect(rope: &Rope) -> Self {
+        let mut counts = [0usize; 6];
+        for line_idx in 0..rope.len_lines() {
+            let line_break = line_break(rope, line_idx).to_string();
+            if let Some(ending) = Self::from_line_break(&line_break) {
+                counts[Self::ALL.iter().position(|&e| e == ending).unwrap()] += 1;
+            }
+        }
+        Self::ALL
+            .iter()
+            .zip(counts.iter())
+            .filter(|(_, &count)| count > 0)
+            .max_by_key(|(_, &count)| count)
+            .map(|(&ending, _)| ending)
+            .unwrap_or_default()
+    }
+}
+
+/// Check if the char breaks lines, the same way as `Rope` does
+pub(crate) fn is_line_break_char(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\r' | '\u{000B}' | '\u{000C}' | '\u{0085}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+/// Find the nearest grapheme boundary after `char_idx`.
+/// Returns the rope's length if there is none.
+pub(crate) fn next_grapheme_boundary(rope: &Rope, char_idx: usize) -> usize {
+    let byte_idx = rope.char_to_byte(char_idx);
+    let (mut chunk, mut chunk_byte_idx, mut chunk_char_idx, _) = rope.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, rope.len_bytes(), true);
+
+    loop {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return rope.len_chars(),
+            Ok(Some(n)) => return chunk_char_idx + byte_to_char_idx(chunk, n - chunk_byte_idx),
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                let (c, _, c_char_idx, _) = rope.chunk_at_byte(chunk_byte_idx);
+                chunk = c;
+                chunk_char_idx = c_char_idx;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = rope.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => unreachable!("Grapheme cursor got all required context"),
+        }
+    }
+}
+
+/// Find the nearest grapheme boundary before `char_idx`.
+/// Returns 0 if there is none.
+pub(crate) fn prev_grapheme_boundary(rope: &Rope, char_idx: usize) -> usize {
+    let byte_idx = rope.char_to_byte(char_idx);
+    let (mut chunk, mut chunk_byte_idx, mut chunk_char_idx, _) = rope.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, rope.len_bytes(), true);
+
+    loop {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return 0,
+            Ok(Some(n)) => return chunk_char_idx + byte_to_char_idx(chunk, n - chunk_byte_idx),
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (c, c_byte_idx, c_char_idx, _) = rope.chunk_at_byte(chunk_byte_idx - 1);
+                chunk = c;
+                chunk_byte_idx = c_byte_idx;
+                chunk_char_idx = c_char_idx;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = rope.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => unreachable!("Grapheme cursor got all required context"),
+        }
+    }
+}
+
+/// Default word chars: alphanumerics and underscore
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Classifier of chars words are made of, which could capture its settings
+#[derive(Clone)]
+pub(crate) struct WordChars(Arc<dyn Fn(char) -> bool + Send + Sync>);
+
+impl WordChars {
+    pub(crate) fn new<F>(is_word_char: F) -> Self
+    where
+        F: Fn(char) -> bool + Send + Sync + 'static,
+    {
+        WordChars(Arc::new(is_word_char))
+    }
+
+    /// Check if `c` is a word char
+    pub(crate) fn contains(&self, c: char) -> bool {
+        (self.0)(c)
+    }
+}
+
+impl Default for WordChars {
+    fn default() -> Self {
+        WordChars::new(is_word_char)
+    }
+}
+
+impl fmt::Debug for WordChars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WordChars")
+    }
+}
+
+/// Kinds of grapheme clusters words are made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+/// Word boundaries search. Words are sequences of word chars or
+/// sequences of other non-blank chars; "big" words are sequences of any
+/// non-blank chars. Line breaks are blanks too.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Words<'a> {
+    pub(crate) word_chars: &'a WordChars,
+    pub(crate) big: bool,
+}
+
+impl Words<'_> {
+    /// Classify the grapheme cluster starting at `char_idx` by its
+    /// first char; the rope's end is a blank
+    pub(crate) fn class(&self, rope: &Rope, char_idx: usize) -> CharClass {
+        match rope.get_char(char_idx) {
+            None => CharClass::Whitespace,
+            Some(c) if c.is_whitespace() => CharClass::Whitespace,
+            Some(_) if self.big => CharClass::Word,
+            Some(c) if self.word_chars.contains(c) => CharClass::Word,
+            Some(_) => CharClass::Punctuation,
+        }
+    }
+
+    /// Find the start of the next word, like `w` in Vim
+    pub(crate) fn next_start(&self, rope: &Rope, mut char_idx: usize) -> usize {
+        let len = rope.len_chars();
+        let class = self.class(rope, char_idx);
+        while char_idx < len && self.class(rope, char_idx) == class {
+            char_idx = next_grapheme_boundary(rope, char_idx);
+        }
+        while char_idx < len && self.class(rope, char_idx) == CharClass::Whitespace {
+            char_idx = next_grapheme_boundary(rope, char_idx);
+        }
+        char_idx
+    }
+
+    /// Find the start of the current word or the previous one if already
+    /// at the start, like `b` in Vim
+    pub(crate) fn prev_start(&self, rope: &Rope, mut char_idx: usize) -> usize {
+        char_idx = prev_grapheme_boundary(rope, char_idx);
+        while char_idx > 0 && self.class(rope, char_idx) == CharClass::Whitespace {
+            char_idx = prev_grapheme_boundary(rope, char_idx);
+        }
+        let class = self.class(rope, char_idx);
+        while char_idx > 0 {
+            let prev = prev_grapheme_boundary(rope, char_idx);
+            if self.class(rope, prev) != class {
+                break;
+            }
+            char_idx = prev;
+        }
+        char_idx
+    }
+
+    /// Find the end of the current word or the next one if already at
+    /// the end, like `e` in Vim
+    pub(crate) fn next_end(&self, rope: &Rope, mut char_idx: usize) -> usize {
+        let len = rope.len_chars();
+        char_idx = next_grapheme_boundary(rope, char_idx);
+        while char_idx < len && self.class(rope, char_idx) == CharClass::Whitespace {
+            char_idx = next_grapheme_boundary(rope, char_idx);
+        }
+        let class = self.class(rope, char_idx);
+        while char_idx < len {
+            let next = next_grapheme_boundary(rope, char_idx);
+            if next >= len || self.class(rope, next) != class {
+                break;
+            }
+            char_idx = next;
+        }
+        char_idx
+    }
+}
+
+/// Default bracket pairs
+pub(crate) const BRACKET_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+/// Find an index of the bracket paired with one at `char_idx` skipping nested
+/// pairs of the same kind; returns `None` if there is no bracket at `char_idx`
+/// or it is unbalanced.
+pub(crate) fn matching_bracket(
+    rope: &Rope,
+    char_idx: usize,
+    pairs: &[(char, char)],
+) -> Option<usize> {
+    let c = rope.get_char(char_idx)?;
+    let mut depth = 0usize;
+    if let Some(&(open, close)) = pairs.iter().find(|(open, _)| *open == c) {
+        for (idx, c) in rope.chars_at(char_idx).enumerate() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(char_idx + idx);
+                }
+            }
+        }
+    } else if let Some(&(open, close)) = pairs.iter().find(|(_, close)| *close == c) {
+        let mut chars = rope.chars_at(char_idx + 1);
+        let mut idx = char_idx + 1;
+        while let Some(c) = chars.prev() {
+            idx -= 1;
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Find an index of the `n`-th occurrence of `c` after `char_idx` or
+/// before it if not `forward`, looking within `bounds` only
+pub(crate) fn find_char(
+    rope: &Rope,
+    char_idx: usize,
+    c: char,
+    forward: bool,
+    n: usize,
+    bounds: Range<usize>,
+) -> Option<usize> {
+    let n = n.checked_sub(1)?;
+    if forward {
+        let from = (char_idx + 1).max(bounds.start);
+        if from >= bounds.end {
+            return None;
+        }
+        (from..bounds.end)
+            .zip(rope.chars_at(from))
+            .filter(|&(_, ch)| ch == c)
+            .nth(n)
+            .map(|(idx, _)| idx)
+    } else {
+        let to = char_idx.min(bounds.end);
+        if to <= bounds.start {
+            return None;
+        }
+        let mut chars = rope.chars_at(to);
+        (bounds.start..to)
+            .rev()
+            .filter(|_| chars.prev() == Some(c))
+            .nth(n)
+    }
+}
+
+/// Get an index of the first non-blank char of the line; for a blank
+/// line it is the line's content end
+pub(crate) fn first_non_blank(rope: &Rope, line_idx: usize) -> usize {
+    let line_start = rope.line_to_char(line_idx);
+    let line_end = line_content_end(rope, line_idx);
+    (line_start..line_end)
+        .find(|&char_idx| !rope.char(char_idx).is_whitespace())
+        .unwrap_or(line_end)
+}
+
+/// A unit lines are indented with
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IndentUnit {
+    /// One tab
+    Tab,
+    /// A number of spaces
+    Spaces(usize),
+}
+
+impl Default for IndentUnit {
+    fn default() -> Self {
+        IndentUnit::Spaces(4)
+    }
+}
+
+impl IndentUnit {
+    /// Get the text of `n` units
+    pub(crate) fn repeat(&self, n: usize) -> String {
+        match self {
+            IndentUnit::Tab => "\t".repeat(n),
+            IndentUnit::Spaces(width) => " ".repeat(width * n),
+        }
+    }
+
+    /// Count leading chars of the line making up to `n` units: a tab is
+    /// a whole unit, a space is a column of the `Spaces` unit; with the
+    /// `Tab` unit only tabs are taken.
+    pub(crate) fn leading_len(&self, rope: &Rope, line_idx: usize, n: usize) -> usize {
+        let width = match self {
+            IndentUnit::Tab => 1,
+            IndentUnit::Spaces(width) => *width,
+        };
+        let mut columns = 0;
+        let mut len = 0;
+        for c in rope.line(line_idx).chars() {
+            let char_columns = match (self, c) {
+                (_, '\t') => width,
+                (IndentUnit::Spaces(_), ' ') => 1,
+                _ => break,
+            };
+            if columns + char_columns > width * n {
+                break;
+            }
+            columns += char_columns;
+            len += 1;
+        }
+        len
+    }
+}
+
+/// How new lines are indented when line breaks are inserted
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum AutoIndent {
+    /// New lines start at column 1
+    #[default]
+    Off,
+    /// New lines copy leading blanks of lines they are broken from
+    Keep,
+    /// Like `Keep`, but one more indentation unit is added after an
+    /// opening bracket
+    AfterBrackets,
+}
+
+/// Indent lines started by line breaks of `text` which is inserted after
+/// `line_prefix`, the beginning of a line
+pub(crate) fn auto_indent(
+    text: &str,
+    line_prefix: &str,
+    mode: AutoIndent,
+    indent_unit: IndentUnit,
+    opening_brackets: impl Fn(char) -> bool,
+) -> String {
+    if mode == AutoIndent::Off {
+        return text.to_owned();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut line = line_prefix.to_owned();
+    for piece in text.split_inclusive('\n') {
+        result.push_str(piece);
+        if !piece.ends_with('\n') {
+            break;
+        }
+        line.push_str(piece);
+        let mut indentation: String = line
+            .chars()
+            .take_while(|&c| c == ' ' || c == '\t')
+            .collect();
+        let last_char = line.trim_end().chars().last();
+        if mode == AutoIndent::AfterBrackets && matches!(last_char, Some(c) if opening_brackets(c))
+        {
+            indentation.push_str(&indent_unit.repeat(1));
+        }
+        result.push_str(&indentation);
+        line = indentation;
+    }
+    result
+}
+
+/// Get an index of the char which ends the line's contents, that is
+/// the first char of a line break or the rope's length for the last line
+pub(crate) fn line_content_end(rope: &Rope, line_idx: usize) -> usize {
+    let line = rope.line(line_idx);
+    let mut chars = line.chars_at(line.len_chars());
+    let line_break_len = match (chars.prev(), chars.prev()) {
+        (Some('\n'), Some('\r')) => 2,
+        (Some(c), _) if is_line_break_char(c) => 1,
+        _ => 0,
+    };
+    rope.line_to_char(line_idx) + line.len_chars() - line_break_len
+}
+
+/// Get the line break which ends the line, it is empty for the last line
+pub(crate) fn line_break(rope: &Rope, line_idx: usize) -> ropey::RopeSlice {
+    let line_end = rope.line_to_char(line_idx) + rope.line(line_idx).len_chars();
+    rope.slice(line_content_end(rope, line_idx)..line_end)
+}
+
+/// Get a number of display columns the grapheme cluster takes if it starts
+/// at display column `x`: a tab reaches the next tab stop, wide chars take
+/// two columns and the rest takes at least one so a cursor could be seen
+pub(crate) fn grapheme_width(grapheme: &str, x: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - x % tab_width
+    } else {
+        grapheme.width().max(1)
+    }
+}
+
+/// Get a display column, starting from 0, where the grapheme cluster at
+/// column `col` of the line starts
+pub(crate) fn display_col(rope: &Rope, line_idx: usize, col: usize, tab_width: usize) -> usize {
+    let line_end = line_content_end(rope, line_idx);
+    let mut char_idx = rope.line_to_char(line_idx);
+    let mut x = 0;
+    for _ in 1..col {
+        if char_idx >= line_end {
+            break;
+        }
+        let next = next_grapheme_boundary(rope, char_idx);
+        x += grapheme_width(&Cow::from(rope.slice(char_idx..next)), x, tab_width);
+        char_idx = next;
+    }
+    x
+}
+
+/// Get a column of the grapheme cluster of the line which covers display
+/// column `x`; for `x` past the line's contents it is the line break's one
+pub(crate) fn col_at_display_col(
+    rope: &Rope,
+    line_idx: usize,
+    x: usize,
+    tab_width: usize,
+) -> usize {
+    let line_end = line_content_end(rope, line_idx);
+    let mut char_idx = rope.line_to_char(line_idx);
+    let mut col = 1;
+    let mut start = 0;
+    while char_idx < line_end {
+        let next = next_grapheme_boundary(rope, char_idx);
+        let width = grapheme_width(&Cow::from(rope.slice(char_idx..next)), start, tab_width);
+        if start + width > x {
+            break;
+        }
+        start += width;
+        char_idx = next;
+        col += 1;
+    }
+    col
+}
+
+/// Count grapheme clusters between two char indices
+pub(crate) fn graphemes_count(rope: &Rope, from: usize, to: usize) -> usize {
+    let mut count = 0;
+    let mut char_idx = from;
+    while char_idx < to {
+        char_idx = next_grapheme_boundary(rope, char_idx);
+        count += 1;
+    }
+    count
+}
+
+/// Find non-empty matches of `regex` within chars `range` of the rope,
+/// giving char ranges of them
+pub(crate) fn find_matches(rope: &Rope, regex: &Regex, range: Range<usize>) -> Vec<Range<usize>> {
+    let text = rope.slice(range.clone()).to_string();
+    let mut byte_idx = 0;
+    let mut char_idx = range.start;
+    let mut to_char_idx = |byte| {
+        char_idx += byte_to_char_idx(&text[byte_idx..], byte - byte_idx);
+        byte_idx = byte;
+        char_idx
+    };
+    regex
+        .find_iter(&text)
+        .filter(|m| !m.as_str().is_empty())
+        .map(|m| to_char_idx(m.start())..to_char_idx(m.end()))
+        .collect()
+}
+
+/// Split chars `range` of the rope by matches of `regex`, giving char
+/// ranges of non-empty pieces between them
+pub(crate) fn split_by_matches(
+    rope: &Rope,
+    regex: &Regex,
+    range: Range<usize>,
+) -> Vec<Range<usize>> {
+    let mut pieces = vec![];
+    let mut start = range.start;
+    for separator in find_matches(rope, regex, range.clone()) {
+        pieces.push(start..separator.start);
+        start = separator.end;
+    }
+    pieces.push(start..range.end);
+    pieces.retain(|piece| !piece.is_empty());
+    pieces
+}
+
+/// Split chars `range` of the rope into pieces within one line each,
+/// line breaks are kept at the end of pieces
+pub(crate) fn split_by_lines(rope: &Rope, range: Range<usize>) -> Vec<Range<usize>> {
+    if range.is_empty() {
+        return vec![];
+    }
+    let first_line = rope.char_to_line(range.start);
+    let last_line = rope.char_to_line(range.end - 1);
+    (first_line..=last_line)
+        .map(|line_idx| {
+            let line_start = rope.line_to_char(line_idx);
+            let line_end = line_start + rope.line(line_idx).len_chars();
+            range.start.max(line_start)..range.end.min(line_end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundaries() {
+        let rope = Rope::from_str("ae\u{301}🇷🇺\r\nb");
+        assert_eq!(next_grapheme_boundary(&rope, 0), 1);
+        assert_eq!(next_grapheme_boundary(&rope, 1), 3);
+        assert_eq!(next_grapheme_boundary(&rope, 3), 5);
+        assert_eq!(next_grapheme_boundary(&rope, 5), 7);
+        assert_eq!(next_grapheme_boundary(&rope, 8), 8);
+    }
+
+    #[test]
+    fn test_display_col() {
+        let rope = Rope::from_str("a\tb世界e\u{301}\n\tx");
+        assert_eq!(display_col(&rope, 0, 1, 4), 0);
+        assert_eq!(display_col(&rope, 0, 2, 4), 1);
+        assert_eq!(display_col(&rope, 0, 3, 4), 4);
+        assert_eq!(display_col(&rope, 0, 4, 4), 5);
+        assert_eq!(display_col(&rope, 0, 5, 4), 7);
+        assert_eq!(display_col(&rope, 0, 6, 4), 9);
+        assert_eq!(display_col(&rope, 0, 7, 4), 10);
+        assert_eq!(display_col(&rope, 0, 3, 8), 8);
+        assert_eq!(display_col(&rope, 1, 2, 4), 4);
+    }
+
+    #[test]
+    fn test_col_at_display_col() {
+        let rope = Rope::from_str("a\tb世界e\u{301}\n\tx");
+        assert_eq!(col_at_display_col(&rope, 0, 0, 4), 1);
+        assert_eq!(col_at_display_col(&rope, 0, 1, 4), 2);
+        assert_eq!(col_at_display_col(&rope, 0, 3, 4), 2);
+        assert_eq!(col_at_display_col(&rope, 0, 4, 4), 3);
+        assert_eq!(col_at_display_col(&rope, 0, 6, 4), 4);
+        assert_eq!(col_at_display_col(&rope, 0, 8, 4), 5);
+        assert_eq!(col_at_display_col(&rope, 0, 9, 4), 6);
+        assert_eq!(col_at_display_col(&rope, 0, 100, 4), 7);
+        assert_eq!(col_at_display_col(&rope, 1, 2, 4), 1);
+    }
+
+    #[test]
+    fn test_line_content_end() {
+        let rope = Rope::from_str("ab\r\ncd\n\nef");
+        assert_eq!(line_content_end(&rope, 0), 2);
+        assert_eq!(line_content_end(&rope, 1), 6);
+        assert_eq!(line_content_end(&rope, 2), 7);
+        assert_eq!(line_content_end(&rope, 3), 10);
+    }
+
+    #[test]
+    fn test_line_content_end_unicode_breaks() {
+        let rope = Rope::from_str("a\u{2028}b\u{0085}\rc\u{000C}d");
+        assert_eq!(line_content_end(&rope, 0), 1);
+        assert_eq!(line_content_end(&rope, 1), 3);
+        assert_eq!(line_content_end(&rope, 2), 4);
+        assert_eq!(line_content_end(&rope, 3), 6);
+        assert_eq!(line_content_end(&rope, 4), 8);
+    }
+
+    #[test]
+    fn test_detect_line_ending() {
+        let rope = Rope::from_str("a\r\nb\nc\r\nd");
+        assert_eq!(LineEnding::detect(&rope), LineEnding::CrLf);
+        let rope = Rope::from_str("a\u{2028}b\u{000C}c");
+        assert_eq!(LineEnding::detect(&rope), LineEnding::Ls);
+        let rope = Rope::from_str("abc");
+        assert_eq!(LineEnding::detect(&rope), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_graphemes_count() {
+        let rope = Rope::from_str("ae\u{301}🇷🇺\r\nb");
+        assert_eq!(graphemes_count(&rope, 0, 5), 3);
+        assert_eq!(graphemes_count(&rope, 0, 8), 5);
+    }
+
+    #[test]
+    fn test_prev_grapheme_boundary() {
+        let rope = Rope::from_str("ae\u{301}🇷🇺\r\nb");
+        assert_eq!(prev_grapheme_boundary(&rope, 8), 7);
+        assert_eq!(prev_grapheme_boundary(&rope, 7), 5);
+        assert_eq!(prev_grapheme_boundary(&rope, 5), 3);
+        assert_eq!(prev_grapheme_boundary(&rope, 3), 1);
+        assert_eq!(prev_grapheme_boundary(&rope, 1), 0);
+        assert_eq!(prev_grapheme_boundary(&rope, 0), 0);
+    }
+
+    #[test]
+    fn test_word_boundaries() {
+        let rope = Rope::from_str("foo.bar(baz)  qux\n\n  end");
+        let word_chars = WordChars::default();
+        let words = Words {
+            word_chars: &word_chars,
+            big: false,
+        };
+        let starts: Vec<usize> = (0..7)
+            .scan(0, |idx, _| {
+                *idx = words.next_start(&rope, *idx);
+                Some(*idx)
+            })
+            .collect();
+        assert_eq!(starts, vec![3, 4, 7, 8, 11, 14, 21]);
+        assert_eq!(words.next_start(&rope, 21), 24);
+
+        assert_eq!(words.prev_start(&rope, 24), 21);
+        assert_eq!(words.prev_start(&rope, 21), 14);
+        assert_eq!(words.prev_start(&rope, 16), 14);
+        assert_eq!(words.prev_start(&rope, 4), 3);
+        assert_eq!(words.prev_start(&rope, 2), 0);
+        assert_eq!(words.prev_start(&rope, 0), 0);
+
+        assert_eq!(words.next_end(&rope, 0), 2);
+        assert_eq!(words.next_end(&rope, 2), 3);
+        assert_eq!(words.next_end(&rope, 11), 16);
+        assert_eq!(words.next_end(&rope, 16), 23);
+        assert_eq!(words.next_end(&rope, 23), 24);
+
+        let big_words = Words {
+            word_chars: &word_chars,
+            big: true,
+        };
+        assert_eq!(big_words.next_start(&rope, 0), 14);
+        assert_eq!(big_words.prev_start(&rope, 14), 0);
+        assert_eq!(big_words.next_end(&rope, 0), 11);
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let rope = Rope::from_str("f(a[0], (b)) <x> ]");
+        assert_eq!(matching_bracket(&rope, 1, BRACKET_PAIRS), Some(11));
+        assert_eq!(matching_bracket(&rope, 11, BRACKET_PAIRS), Some(1));
+        assert_eq!(matching_bracket(&rope, 3, BRACKET_PAIRS), Some(5));
+        assert_eq!(matching_bracket(&rope, 15, BRACKET_PAIRS), Some(13));
+        assert_eq!(matching_bracket(&rope, 15, &[('(', ')')]), None);
+        assert_eq!(matching_bracket(&rope, 17, BRACKET_PAIRS), None);
+        assert_eq!(matching_bracket(&rope, 0, BRACKET_PAIRS), None);
+        assert_eq!(matching_bracket(&rope, 18, BRACKET_PAIRS), None);
+    }
+
+    #[test]
+    fn test_find_char() {
+        let rope = Rope::from_str("a,b,c\nd,e");
+        assert_eq!(find_char(&rope, 0, ',', true, 1, 0..5), Some(1));
+        assert_eq!(find_char(&rope, 0, ',', true, 2, 0..5), Some(3));
+        assert_eq!(find_char(&rope, 0, ',', true, 3, 0..5), None);
+        assert_eq!(find_char(&rope, 0, ',', true, 3, 0..9), Some(7));
+        assert_eq!(find_char(&rope, 4, ',', false, 1, 0..5), Some(3));
+        assert_eq!(find_char(&rope, 4, ',', false, 2, 0..5), Some(1));
+        assert_eq!(find_char(&rope, 8, ',', false, 1, 6..9), Some(7));
+        assert_eq!(find_char(&rope, 8, ',', false, 2, 6..9), None);
+        assert_eq!(find_char(&rope, 0, ',', true, 0, 0..5), None);
+    }
+
+    #[test]
+    fn test_indent_unit() {
+        let rope = Rope::from_str("      a\n\t\tb\n  \tc\n");
+        let spaces = IndentUnit::Spaces(4);
+        assert_eq!(spaces.repeat(2), "        ");
+        assert_eq!(IndentUnit::Tab.repeat(2), "\t\t");
+        assert_eq!(spaces.leading_len(&rope, 0, 1), 4);
+        assert_eq!(spaces.leading_len(&rope, 0, 2), 6);
+        assert_eq!(spaces.leading_len(&rope, 1, 1), 1);
+        assert_eq!(spaces.leading_len(&rope, 2, 1), 2);
+        assert_eq!(IndentUnit::Tab.leading_len(&rope, 0, 1), 0);
+        assert_eq!(IndentUnit::Tab.leading_len(&rope, 1, 3), 2);
+    }
+
+    #[test]
+    fn test_auto_indent() {
+        let brackets = |c| c == '{';
+        let unit = IndentUnit::Spaces(2);
+        assert_eq!(
+            auto_indent("\n", "  if a {", AutoIndent::Off, unit, brackets),
+            "\n"
+        );
+        assert_eq!(
+            auto_indent("\n", "  if a {", AutoIndent::Keep, unit, brackets),
+            "\n  "
+        );
+        assert_eq!(
+            auto_indent("\n", "  if a { ", AutoIndent::AfterBrackets, unit, brackets),
+            "\n    "
+        );
+        assert_eq!(
+            auto_indent("x\ny {\nz", "\t", AutoIndent::AfterBrackets, unit, brackets),
+            "x\n\ty {\n\t  z"
+        );
+    }
+
+    #[test]
+    fn test_first_non_blank() {
+        let rope = Rope::from_str("  \tab c\n   \r\nd");
+        assert_eq!(first_non_blank(&rope, 0), 3);
+        assert_eq!(first_non_blank(&rope, 1), 11);
+        assert_eq!(first_non_blank(&rope, 2), 13);
+    }
+
+    #[test]
+    fn test_split_by_matches() {
+        let rope = Rope::from_str("a, b,,c,");
+        let regex = Regex::new(", *").unwrap();
+        assert_eq!(
+            split_by_matches(&rope, &regex, 0..rope.len_chars()),
+            vec![0..1, 3..4, 6..7]
+        );
+        assert_eq!(split_by_matches(&rope, &regex, 2..5), vec![2..4]);
+    }
+
+    #[test]
+    fn test_split_by_lines() {
+        let rope = Rope::from_str("ab\r\ncd\nef");
+        assert_eq!(split_by_lines(&rope, 1..9), vec![1..4, 4..7, 7..9]);
+        assert_eq!(split_by_lines(&rope, 4..7), vec![4..7]);
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let rope = Rope::from_str("привет, мир\nи миру");
+        let regex = Regex::new("ми|x*").unwrap();
+        assert_eq!(
+            find_matches(&rope, &regex, 0..rope.len_chars()),
+            vec![8..10, 14..16]
+        );
+        assert!(find_matches(&rope, &regex, 9..15).is_empty());
+        assert_eq!(find_matches(&rope, &regex, 3..16), vec![8..10, 14..16]);
+    }
+}
@@ -0,0 +1,433 @@
+//! Text objects: regions of text around a cursor, like a word or
+//! contents of brackets, to be selected as a whole
+use crate::text::{
+    line_content_end, matching_bracket, next_grapheme_boundary, prev_grapheme_boundary, CharClass,
+    Words,
+};
+use ropey::Rope;
+use std::ops::Range;
+
+/// Kinds of text objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    /// Contents of the innermost pair of brackets enclosing a selection,
+    /// like `TextObject::Brackets('(', ')')`; if the selection is already
+    /// the object, the enclosing pair is taken
+    Brackets(char, char),
+    /// Contents of quotes on the cursor's line, like
+    /// `TextObject::Quotes('"')`; quotes escaped with a backslash are
+    /// skipped
+    Quotes(char),
+    /// A word under the cursor, see `Buffer::set_word_chars`
+    Word,
+    /// A sequence of non-blank chars under the cursor
+    BigWord,
+    /// A sentence ended with `.`, `!` or `?` or with its paragraph
+    Sentence,
+    /// Lines up to the nearest blank ones
+    Paragraph,
+    /// Lines indented at least as the cursor's one, blank lines inside
+    /// included
+    Indentation,
+}
+
+/// How much of a text object to select
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectScope {
+    /// Contents only, without delimiters or surrounding blanks
+    Inner,
+    /// Contents with delimiters or surrounding blanks
+    Around,
+}
+
+impl TextObject {
+    /// Find chars of the object for a selection covering `range` with
+    /// the cursor at `cursor`; returns `None` if there is no such object
+    /// or it is empty.
+    pub(crate) fn find(
+        self,
+        rope: &Rope,
+        scope: ObjectScope,
+        range: Range<usize>,
+        cursor: usize,
+        words: Words<'_>,
+    ) -> Option<Range<usize>> {
+        let object = match self {
+            TextObject::Brackets(open, close) => brackets(rope, scope, range, open, close),
+            TextObject::Quotes(quote) => quotes(rope, scope, cursor, quote),
+            TextObject::Word => word(
+                rope,
+                scope,
+                cursor,
+                Words {
+                    big: false,
+                    ..words
+                },
+            ),
+            TextObject::BigWord => word(rope, scope, cursor, Words { big: true, ..words }),
+            TextObject::Sentence => sentence(rope, scope, cursor),
+            TextObject::Paragraph => paragraph(rope, scope, cursor),
+            TextObject::Indentation => indentation(rope, scope, cursor),
+        }?;
+        if object.is_empty() {
+            None
+        } else {
+            Some(object)
+        }
+    }
+}
+
+/// Spaces and tabs, but not line breaks
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn is_blank_line(rope: &Rope, line_idx: usize) -> bool {
+    rope.line(line_idx).chars().all(char::is_whitespace)
+}
+
+fn indentation_width(rope: &Rope, line_idx: usize) -> usize {
+    rope.line(line_idx)
+        .chars()
+        .take_while(|&c| is_blank(c))
+        .count()
+}
+
+fn brackets(
+    rope: &Rope,
+    scope: ObjectScope,
+    range: Range<usize>,
+    open: char,
+    close: char,
+) -> Option<Range<usize>> {
+    if range.start >= rope.len_chars() {
+        return None;
+    }
+    let pairs = &[(open, close)];
+    // A cursor on a closing bracket belongs to its pair
+    let mut idx = if rope.get_char(range.start) == Some(close) {
+        matching_bracket(rope, range.start, pairs)?
+    } else {
+        range.start
+    };
+    loop {
+        let mut depth = 0usize;
+        let mut chars = rope.chars_at(idx + 1);
+        let mut start = None;
+        while let Some(c) = chars.prev() {
+            if c == open && depth == 0 {
+                start = Some(idx);
+                break;
+            } else if c == open {
+                depth -= 1;
+            } else if c == close {
+                depth += 1;
+            }
+            idx = idx.checked_sub(1)?;
+        }
+        let start = start?;
+        if let Some(end) = matching_bracket(rope, start, pairs) {
+            let object = match scope {
+                ObjectScope::Inner => start + 1..end,
+                ObjectScope::Around => start..end + 1,
+            };
+            // An object already selected grows to the enclosing one
+            if end + 1 >= range.end && object != range {
+                return Some(object);
+            }
+        }
+        idx = start.checked_sub(1)?;
+    }
+}
+
+fn quotes(rope: &Rope, scope: ObjectScope, cursor: usize, quote: char) -> Option<Range<usize>> {
+    let line_idx = rope.char_to_line(cursor);
+    let line_start = rope.line_to_char(line_idx);
+    let line_end = line_content_end(rope, line_idx);
+    let mut escaped = false;
+    let mut positions = vec![];
+    for (idx, c) in (line_start..line_end).zip(rope.chars_at(line_start)) {
+        if c == quote && !escaped {
+            positions.push(idx);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    let (start, end) = positions
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(start, end)| start <= cursor && cursor <= end)?;
+    Some(match scope {
+        ObjectScope::Inner => start + 1..end,
+        ObjectScope::Around => start..end + 1,
+    })
+}
+
+fn word(rope: &Rope, scope: ObjectScope, cursor: usize, words: Words<'_>) -> Option<Range<usize>> {
+    let len = rope.len_chars();
+    if cursor >= len {
+        return None;
+    }
+    let class = words.class(rope, cursor);
+    let mut start = cursor;
+    while start > 0 && words.class(rope, prev_grapheme_boundary(rope, start)) == class {
+        start = prev_grapheme_boundary(rope, start);
+    }
+    let mut end = next_grapheme_boundary(rope, cursor);
+    while end < len && words.class(rope, end) == class {
+        end = next_grapheme_boundary(rope, end);
+    }
+    if scope == ObjectScope::Around && class != CharClass::Whitespace {
+        let blanks_end = (end..len)
+            .find(|&idx| !is_blank(rope.char(idx)))
+            .unwrap_or(len);
+        if blanks_end > end {
+            end = blanks_end;
+        } else {
+            while start > 0 && is_blank(rope.char(start - 1)) {
+                start -= 1;
+            }
+        }
+    }
+    Some(start..end)
+}
+
+fn sentence(rope: &Rope, scope: ObjectScope, cursor: usize) -> Option<Range<usize>> {
+    let paragraph = paragraph(rope, ObjectScope::Inner, cursor)?;
+    if is_blank_line(rope, rope.char_to_line(cursor)) {
+        return None;
+    }
+    let is_sentence_end = |idx: usize| {
+        matches!(rope.char(idx), '.' | '!' | '?')
+            && (idx + 1 == rope.len_chars() || rope.char(idx + 1).is_whitespace())
+    };
+    let content_end = (paragraph.start..paragraph.end)
+        .rev()
+        .find(|&idx| !rope.char(idx).is_whitespace())
+        .map(|idx| idx + 1)?;
+    let mut start = (paragraph.start..cursor)
+        .rev()
+        .find(|&idx| is_sentence_end(idx))
+        .map(|idx| idx + 1)
+        .unwrap_or(paragraph.start);
+    while start < content_end && rope.char(start).is_whitespace() {
+        start += 1;
+    }
+    let mut end = (start.max(cursor)..content_end)
+        .find(|&idx| is_sentence_end(idx))
+        .map(|idx| idx + 1)
+        .unwrap_or(content_end);
+    if scope == ObjectScope::Around {
+        if end < content_end {
+            while end < content_end && rope.char(end).is_whitespace() {
+                end += 1;
+            }
+        } else {
+            while start > paragraph.start && rope.char(start - 1).is_whitespace() {
+                start -= 1;
+            }
+        }
+    }
+    Some(start..end)
+}
+
+fn paragraph(rope: &Rope, scope: ObjectScope, cursor: usize) -> Option<Range<usize>> {
+    let lines = rope.len_lines();
+    let line_idx = rope.char_to_line(cursor);
+    let blank = is_blank_line(rope, line_idx);
+    let mut first = line_idx;
+    while first > 0 && is_blank_line(rope, first - 1) == blank {
+        first -= 1;
+    }
+    let mut last = line_idx;
+    while last + 1 < lines && is_blank_line(rope, last + 1) == blank {
+        last += 1;
+    }
+    if scope == ObjectScope::Around && !blank {
+        if last + 1 < lines && is_blank_line(rope, last + 1) {
+            while last + 1 < lines && is_blank_line(rope, last + 1) {
+                last += 1;
+            }
+        } else {
+            while first > 0 && is_blank_line(rope, first - 1) {
+                first -= 1;
+            }
+        }
+    }
+    Some(rope.line_to_char(first)..rope.line_to_char(last + 1))
+}
+
+fn indentation(rope: &Rope, scope: ObjectScope, cursor: usize) -> Option<Range<usize>> {
+    let lines = rope.len_lines();
+    let line_idx = rope.char_to_line(cursor);
+    if is_blank_line(rope, line_idx) {
+        return None;
+    }
+    let width = indentation_width(rope, line_idx);
+    let in_block = |idx: usize| is_blank_line(rope, idx) || indentation_width(rope, idx) >= width;
+    let mut first = line_idx;
+    while first > 0 && in_block(first - 1) {
+        first -= 1;
+    }
+    let mut last = line_idx;
+    while last + 1 < lines && in_block(last + 1) {
+        last += 1;
+    }
+    if scope == ObjectScope::Inner {
+        while is_blank_line(rope, first) {
+            first += 1;
+        }
+        while is_blank_line(rope, last) {
+            last -= 1;
+        }
+    }
+    Some(rope.line_to_char(first)..rope.line_to_char(last + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::WordChars;
+
+    fn words(word_chars: &WordChars) -> Words<'_> {
+        Words {
+            word_chars,
+            big: false,
+        }
+    }
+
+    fn find(text: &str, object: TextObject, scope: ObjectScope, cursor: usize) -> Option<String> {
+        let rope = Rope::from_str(text);
+        let word_chars = WordChars::default();
+        object
+            .find(&rope, scope, cursor..cursor + 1, cursor, words(&word_chars))
+            .map(|range| rope.slice(range).to_string())
+    }
+
+    #[test]
+    fn test_brackets() {
+        let text = "f(a, (b), c[0])";
+        let parens = TextObject::Brackets('(', ')');
+        assert_eq!(
+            find(text, parens, ObjectScope::Inner, 3).as_deref(),
+            Some("a, (b), c[0]")
+        );
+        assert_eq!(
+            find(text, parens, ObjectScope::Around, 6).as_deref(),
+            Some("(b)")
+        );
+        assert_eq!(
+            find(text, parens, ObjectScope::Around, 7).as_deref(),
+            Some("(b)")
+        );
+        assert_eq!(find(text, parens, ObjectScope::Inner, 0), None);
+        assert_eq!(find("f()", parens, ObjectScope::Inner, 1), None);
+    }
+
+    #[test]
+    fn test_brackets_enclosing_selection() {
+        let rope = Rope::from_str("[a [b] c]");
+        let object = TextObject::Brackets('[', ']');
+        let word_chars = WordChars::default();
+        assert_eq!(
+            object.find(&rope, ObjectScope::Inner, 3..8, 7, words(&word_chars)),
+            Some(1..8)
+        );
+    }
+
+    #[test]
+    fn test_quotes() {
+        let text = r#"say "hi \"you\"" and 'x'"#;
+        assert_eq!(
+            find(text, TextObject::Quotes('"'), ObjectScope::Inner, 6).as_deref(),
+            Some(r#"hi \"you\""#)
+        );
+        assert_eq!(
+            find(text, TextObject::Quotes('\''), ObjectScope::Around, 22).as_deref(),
+            Some("'x'")
+        );
+        assert_eq!(
+            find(text, TextObject::Quotes('"'), ObjectScope::Inner, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_word() {
+        let text = "foo-bar  baz";
+        assert_eq!(
+            find(text, TextObject::Word, ObjectScope::Inner, 1).as_deref(),
+            Some("foo")
+        );
+        assert_eq!(
+            find(text, TextObject::Word, ObjectScope::Around, 5).as_deref(),
+            Some("bar  ")
+        );
+        assert_eq!(
+            find(text, TextObject::Word, ObjectScope::Around, 10).as_deref(),
+            Some("  baz")
+        );
+        assert_eq!(
+            find(text, TextObject::BigWord, ObjectScope::Inner, 4).as_deref(),
+            Some("foo-bar")
+        );
+    }
+
+    #[test]
+    fn test_sentence() {
+        let text = "One. Two words! Three\nlines.\n\nNext";
+        assert_eq!(
+            find(text, TextObject::Sentence, ObjectScope::Inner, 7).as_deref(),
+            Some("Two words!")
+        );
+        assert_eq!(
+            find(text, TextObject::Sentence, ObjectScope::Around, 0).as_deref(),
+            Some("One. ")
+        );
+        assert_eq!(
+            find(text, TextObject::Sentence, ObjectScope::Inner, 24).as_deref(),
+            Some("Three\nlines.")
+        );
+        assert_eq!(
+            find(text, TextObject::Sentence, ObjectScope::Around, 24).as_deref(),
+            Some(" Three\nlines.")
+        );
+        assert_eq!(
+            find(text, TextObject::Sentence, ObjectScope::Inner, 29),
+            None
+        );
+    }
+
+    #[test]
+    fn test_paragraph() {
+        let text = "a\nb\n\n\nc\n";
+        assert_eq!(
+            find(text, TextObject::Paragraph, ObjectScope::Inner, 2).as_deref(),
+            Some("a\nb\n")
+        );
+        assert_eq!(
+            find(text, TextObject::Paragraph, ObjectScope::Around, 0).as_deref(),
+            Some("a\nb\n\n\n")
+        );
+        assert_eq!(
+            find(text, TextObject::Paragraph, ObjectScope::Inner, 4).as_deref(),
+            Some("\n\n")
+        );
+    }
+
+    #[test]
+    fn test_indentation() {
+        let text = "fn f() {\n    a;\n\n    if b {\n        c;\n    }\n\n}\n";
+        assert_eq!(
+            find(text, TextObject::Indentation, ObjectScope::Inner, 12).as_deref(),
+            Some("    a;\n\n    if b {\n        c;\n    }\n")
+        );
+        assert_eq!(
+            find(text, TextObject::Indentation, ObjectScope::Around, 12).as_deref(),
+            Some("    a;\n\n    if b {\n        c;\n    }\n\n")
+        );
+        assert_eq!(
+            find(text, TextObject::Indentation, ObjectScope::Inner, 16),
+            None
+        );
+    }
+}
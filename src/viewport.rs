@@ -0,0 +1,113 @@
+//! Visible part of the text which follows the main cursor
+use std::ops::RangeInclusive;
+
+/// A window the text is displayed in: a range of visible lines and a range
+/// of visible display columns.
+///
+/// Lines are counted as a whole, so with soft wrap a line taking several
+/// visual rows still counts once.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Viewport {
+    /// The first visible line
+    pub top_line: usize,
+    /// The first visible display column, starting from 0
+    pub left_col: usize,
+    /// Count of visible lines
+    pub height: usize,
+    /// Count of visible display columns
+    pub width: usize,
+    /// Count of lines kept visible above and below the main cursor when
+    /// scrolling, if the viewport is high enough
+    pub scrolloff: usize,
+}
+
+impl Viewport {
+    /// Create a viewport at the beginning of the text with no scroll margins
+    pub fn new(height: usize, width: usize) -> Self {
+        Viewport {
+            top_line: 1,
+            left_col: 0,
+            height,
+            width,
+            scrolloff: 0,
+        }
+    }
+
+    /// Set count of lines kept visible around the main cursor
+    pub fn with_scrolloff(mut self, scrolloff: usize) -> Self {
+        self.scrolloff = scrolloff;
+        self
+    }
+
+    /// Get the last visible line
+    pub fn bottom_line(&self) -> usize {
+        self.top_line + self.height.max(1) - 1
+    }
+
+    /// Get the range of visible lines
+    pub fn lines(&self) -> RangeInclusive<usize> {
+        self.top_line..=self.bottom_line()
+    }
+
+    /// Scroll as little as possible to make `line` visible along with
+    /// `scrolloff` lines around it, but not beyond the last line
+    /// `lines_count`
+    pub fn scroll_to_line(&mut self, line: usize, lines_count: usize) {
+        let height = self.height.max(1);
+        let margin = self.scrolloff.min((height - 1) / 2);
+        let lowest = (line + margin).min(lines_count.max(line));
+        let min_top = (lowest + 1).saturating_sub(height).max(1);
+        let max_top = line.saturating_sub(margin).max(1);
+        self.top_line = self.top_line.clamp(min_top, max_top);
+    }
+
+    /// Scroll as little as possible to make the display column `x` visible
+    pub fn scroll_to_col(&mut self, x: usize) {
+        let width = self.width.max(1);
+        self.left_col = self.left_col.clamp((x + 1).saturating_sub(width), x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_to_line() {
+        let mut viewport = Viewport::new(5, 10).with_scrolloff(1);
+        viewport.scroll_to_line(4, 100);
+        assert_eq!(viewport.lines(), 1..=5);
+        viewport.scroll_to_line(5, 100);
+        assert_eq!(viewport.lines(), 2..=6);
+        viewport.scroll_to_line(20, 100);
+        assert_eq!(viewport.lines(), 17..=21);
+        viewport.scroll_to_line(18, 100);
+        assert_eq!(viewport.lines(), 17..=21);
+        viewport.scroll_to_line(17, 100);
+        assert_eq!(viewport.lines(), 16..=20);
+        viewport.scroll_to_line(1, 100);
+        assert_eq!(viewport.lines(), 1..=5);
+        viewport.scroll_to_line(20, 20);
+        assert_eq!(viewport.lines(), 16..=20);
+    }
+
+    #[test]
+    fn test_scrolloff_limited_by_height() {
+        let mut viewport = Viewport::new(4, 10).with_scrolloff(10);
+        viewport.scroll_to_line(10, 100);
+        assert_eq!(viewport.lines(), 8..=11);
+    }
+
+    #[test]
+    fn test_scroll_to_col() {
+        let mut viewport = Viewport::new(5, 10);
+        viewport.scroll_to_col(9);
+        assert_eq!(viewport.left_col, 0);
+        viewport.scroll_to_col(12);
+        assert_eq!(viewport.left_col, 3);
+        viewport.scroll_to_col(5);
+        assert_eq!(viewport.left_col, 3);
+        viewport.scroll_to_col(1);
+        assert_eq!(viewport.left_col, 1);
+    }
+}